@@ -0,0 +1,61 @@
+//! How long `PathFinder` takes to cross a large map, with nothing in the way
+//! and through a maze that sends the path back and forth across all of it.
+//!
+//! Run with `cargo bench --bench pathfinding`.
+
+use amethyst::core::math::Point3;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use dd::{
+    grid::MovementMode,
+    level::{Level, LevelTile},
+    pathfinding::PathFinder,
+};
+
+const SIZE: usize = 256;
+
+/// A `size` square map with nothing in the way.
+fn open(size: usize) -> Level {
+    Level::filled(1, "Open", size, size, LevelTile::Plain)
+}
+
+/// A `size` square map crossed by fences with a single gap each,
+/// alternating ends, so the path has to zig-zag across the whole map.
+fn maze(size: usize) -> Level {
+    let rows: Vec<String> = (0..size)
+        .map(|row| match row % 4 {
+            1 => format!("{}.", "-".repeat(size - 1)),
+            3 => format!(".{}", "-".repeat(size - 1)),
+            _ => ".".repeat(size),
+        })
+        .collect();
+    Level::from_ascii(1, "Maze", &rows.join("\n")).unwrap()
+}
+
+fn pathfinding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pathfinding");
+    let (start, corner) = (
+        Point3::new(0, 0, 0),
+        Point3::new(SIZE as u32 - 1, SIZE as u32 - 1, 0),
+    );
+
+    let level = open(SIZE);
+    let finder = PathFinder::new(&level);
+    group.bench_function("open_256", |b| b.iter(|| finder.find(start, corner)));
+
+    let finder = PathFinder::new(&level).with_mode(MovementMode::EightWay);
+    group.bench_function("open_256_eight_way", |b| {
+        b.iter(|| finder.find(start, corner))
+    });
+
+    let level = maze(SIZE);
+    let finder = PathFinder::new(&level);
+    let end = Point3::new(0, SIZE as u32 - 1, 0);
+    assert!(finder.find(start, end).is_some());
+    group.bench_function("maze_256", |b| b.iter(|| finder.find(start, end)));
+
+    group.finish();
+}
+
+criterion_group!(benches, pathfinding);
+criterion_main!(benches);
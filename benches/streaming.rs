@@ -0,0 +1,203 @@
+//! What a frame of the map costs as the camera pans across levels from a
+//! few screens across to far bigger than would fit in memory a tile at a
+//! time: looking around from the tile under the camera, then building the
+//! chunks it nears and deleting those it leaves. Before timing each size,
+//! prints how much memory the game holds after a lap of the level.
+//!
+//! Run with `cargo bench --bench streaming`.
+
+use amethyst::{
+    core::{
+        math::{Point2, Point3, Vector3},
+        Parent, Transform,
+    },
+    ecs::{Builder, Entity, RunNow, World, WorldExt},
+    renderer::Camera,
+    utils::ortho_camera::{CameraNormalizeMode, CameraOrtho, CameraOrthoWorldCoordinates},
+    window::ScreenDimensions,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use dd::{
+    autotile::Autotiles,
+    chunk::MapGrid,
+    data::Terrain,
+    fov::Fog,
+    level::{Level, LevelTile},
+    states::game::{TerrainMap, TileMap},
+    system::chunks::ChunkStreamingSystem,
+};
+
+/// Keeps count of the bytes allocated and not yet freed.
+struct Counting;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let p = System.alloc(layout);
+        if !p.is_null() {
+            LIVE.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        p
+    }
+
+    unsafe fn dealloc(&self, p: *mut u8, layout: Layout) {
+        System.dealloc(p, layout);
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const SIZES: [usize; 3] = [256, 4096, 16384];
+
+/// Tiles the camera moves each frame, a fast pan.
+const PAN: u32 = 4;
+
+/// A `size` square sea with the same few walled islands near its middle row
+/// however big it is.
+fn islands(size: usize) -> Level {
+    let mut level = Level::filled(1, "Islands", size, size, LevelTile::Water);
+    level.sight = Some(8);
+    let y = size as u32 / 2;
+    for &x in &[10, 60, 130, 200] {
+        for dx in 0..40 {
+            for dy in 0..24 {
+                let edge = dx == 0 || dy == 0 || dx == 39 || dy == 23;
+                let tile = if edge {
+                    LevelTile::Wall
+                } else if (dx + dy) % 5 == 0 {
+                    LevelTile::Plain
+                } else {
+                    LevelTile::Grass
+                };
+                level.set_tile(Point2::new(x + dx, y - 12 + dy), tile);
+            }
+        }
+    }
+    level
+}
+
+fn terrain() -> Terrain {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/data/terrain.yaml");
+    serde_yaml::from_reader(std::fs::File::open(path).unwrap()).unwrap()
+}
+
+/// A game on `level`, with just what the map needs to be streamed in.
+struct Game {
+    world: World,
+    streaming: ChunkStreamingSystem,
+    camera: Entity,
+    /// The tile under the camera.
+    at: Point2<u32>,
+}
+
+impl Game {
+    fn new(level: Level) -> Self {
+        let mut world = World::new();
+        world.register::<Transform>();
+        world.register::<Parent>();
+        world.register::<Camera>();
+        world.register::<CameraOrtho>();
+        world.register::<TileMap>();
+
+        let grid = MapGrid::new(
+            Vector3::new(level.width as u32, level.height as u32, 1),
+            Vector3::new(32, 32, 1),
+        );
+        let at = Point2::new(0, level.height as u32 / 2);
+        world.insert(Autotiles::new(&level, &terrain()));
+        world.insert(Fog::default());
+        world.insert(level);
+        world.insert(grid);
+        world.insert(ScreenDimensions::new(800, 600, 1.0));
+        let root = world.create_entity().with(Transform::default()).build();
+        world.insert(TerrainMap {
+            root,
+            sheets: vec![None],
+        });
+        let camera = world
+            .create_entity()
+            .with(Transform::default())
+            .with(Camera::standard_2d(800.0, 600.0))
+            .with(CameraOrtho::new(
+                CameraNormalizeMode::Contain,
+                CameraOrthoWorldCoordinates {
+                    left: -400.0,
+                    right: 400.0,
+                    top: -300.0,
+                    bottom: 300.0,
+                },
+            ))
+            .build();
+
+        let mut game = Self {
+            world,
+            streaming: ChunkStreamingSystem::default(),
+            camera,
+            at,
+        };
+        game.frame();
+        game
+    }
+
+    /// Pans the camera east, round to the west edge on reaching the east.
+    fn frame(&mut self) {
+        let width = self.world.read_resource::<Level>().width as u32;
+        self.at.x = (self.at.x + PAN) % width;
+        {
+            let level = self.world.read_resource::<Level>();
+            self.world
+                .write_resource::<Fog>()
+                .update(&level, self.at, 0);
+        }
+        let translation = self
+            .world
+            .read_resource::<MapGrid>()
+            .to_world(&Point3::new(self.at.x, self.at.y, 0));
+        self.world
+            .write_storage::<Transform>()
+            .get_mut(self.camera)
+            .expect("Camera should have a transform")
+            .set_translation(translation);
+        self.streaming.run_now(&self.world);
+        self.world.maintain();
+    }
+
+    /// Pans once across the whole level.
+    fn lap(&mut self) {
+        let width = self.world.read_resource::<Level>().width as u32;
+        for _ in 0..width / PAN {
+            self.frame();
+        }
+    }
+}
+
+fn streaming(c: &mut Criterion) {
+    let mut group = c.benchmark_group("streaming");
+    for &size in &SIZES {
+        let before = LIVE.load(Ordering::Relaxed);
+        let mut game = Game::new(islands(size));
+        game.lap();
+        let resident = LIVE.load(Ordering::Relaxed).saturating_sub(before);
+        println!(
+            "{0}x{0} level: {1} KiB resident after a lap",
+            size,
+            resident / 1024
+        );
+
+        group.bench_with_input(BenchmarkId::new("frame", size), &size, |b, _| {
+            b.iter(|| game.frame())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, streaming);
+criterion_main!(benches);
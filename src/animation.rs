@@ -0,0 +1,76 @@
+use crate::grid::Direction;
+
+/// Layout of a character sprite sheet: a row for each facing, in the order
+/// south, west, east, north, and in each row a walk cycle that starts from
+/// the standing pose.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterSheet {
+    /// Frames in each row.
+    pub walk_frames: usize,
+    /// Frames shown over one tile of walking. Each step starts half way
+    /// along the cycle from the one before, so feet alternate.
+    pub frames_per_step: usize,
+}
+
+impl Default for CharacterSheet {
+    fn default() -> Self {
+        Self {
+            walk_frames: 4,
+            frames_per_step: 2,
+        }
+    }
+}
+
+impl CharacterSheet {
+    /// The sprite facing `facing`, `progress` of the way through step number
+    /// `stride`, or standing still when not walking.
+    pub fn sprite_number(&self, facing: Direction, walking: Option<(u32, f32)>) -> usize {
+        let frame = match walking {
+            Some((stride, progress)) => {
+                let into_step = (progress.max(0.0) * self.frames_per_step as f32) as usize;
+                let first = (stride as usize % 2) * self.frames_per_step;
+                (first + into_step.min(self.frames_per_step - 1)) % self.walk_frames
+            }
+            None => 0,
+        };
+        facing_row(facing) * self.walk_frames + frame
+    }
+}
+
+/// Diagonals use the row for their east or west part, so characters walking
+/// at an angle look the way they are going across the screen.
+fn facing_row(facing: Direction) -> usize {
+    match facing {
+        Direction::South => 0,
+        Direction::West | Direction::NorthWest | Direction::SouthWest => 1,
+        Direction::East | Direction::NorthEast | Direction::SouthEast => 2,
+        Direction::North => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standing_uses_the_first_frame() {
+        let sheet = CharacterSheet::default();
+        assert_eq!(sheet.sprite_number(Direction::South, None), 0);
+        assert_eq!(sheet.sprite_number(Direction::West, None), 4);
+        assert_eq!(sheet.sprite_number(Direction::East, None), 8);
+        assert_eq!(sheet.sprite_number(Direction::North, None), 12);
+        assert_eq!(sheet.sprite_number(Direction::NorthEast, None), 8);
+    }
+
+    #[test]
+    fn steps_alternate_feet() {
+        let sheet = CharacterSheet::default();
+        let frames: Vec<_> = [(0, 0.0), (0, 0.6), (1, 0.0), (1, 0.6), (2, 0.1)]
+            .iter()
+            .map(|&walking| sheet.sprite_number(Direction::North, Some(walking)))
+            .collect();
+        assert_eq!(frames, vec![12, 13, 14, 15, 12]);
+        // Never runs past the step's own frames
+        assert_eq!(sheet.sprite_number(Direction::South, Some((0, 1.0))), 1);
+    }
+}
@@ -0,0 +1,109 @@
+use amethyst::{
+    assets::{AssetStorage, Format, Handle, Loader},
+    audio::SourceHandle,
+    renderer::{sprite::SpriteSheetHandle, ImageFormat, SpriteSheet, SpriteSheetFormat, Texture},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{
+    data::{ItemList, MonsterList, Terrain},
+    level::Level,
+};
+
+/// Everything the `Loading` state fetches before the game starts, read from
+/// `resources/manifest.ron`. Paths are relative to the `resources` directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssetManifest {
+    pub sprite_sheets: Vec<SpriteSheetEntry>,
+    /// Sheets levels are drawn with, loaded once a level needs them.
+    pub tilesets: Vec<SpriteSheetEntry>,
+    pub levels: Vec<String>,
+    pub monsters: Vec<String>,
+    pub items: Vec<String>,
+    pub terrain: Option<String>,
+    pub audio: Vec<AudioEntry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpriteSheetEntry {
+    pub name: String,
+    pub texture: String,
+    pub sheet: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioEntry {
+    pub name: String,
+    pub path: String,
+}
+
+/// Handles to everything listed in the `AssetManifest`, inserted as a
+/// resource once loading has finished.
+#[derive(Debug, Default, Clone)]
+pub struct GameAssets {
+    pub sprite_sheets: HashMap<String, SpriteSheetHandle>,
+    /// Tilesets from the manifest, by name, not loaded until fetched.
+    pub tilesets: HashMap<String, SpriteSheetEntry>,
+    pub levels: Vec<Handle<Level>>,
+    pub monsters: Vec<Handle<MonsterList>>,
+    pub items: Vec<Handle<ItemList>>,
+    pub terrain: Option<Handle<Terrain>>,
+    pub audio: HashMap<String, SourceHandle>,
+}
+
+impl GameAssets {
+    pub fn sprite_sheet(&self, name: &str) -> Option<SpriteSheetHandle> {
+        self.sprite_sheets.get(name).cloned()
+    }
+
+    /// Whether the manifest lists a sprite sheet or tileset called `name`.
+    pub fn has_sprite_sheet(&self, name: &str) -> bool {
+        self.sprite_sheets.contains_key(name) || self.tilesets.contains_key(name)
+    }
+
+    /// The sheet called `name`, loading it from the manifest's tilesets the
+    /// first time it's asked for.
+    pub fn fetch_sprite_sheet(
+        &mut self,
+        name: &str,
+        loader: &Loader,
+        textures: &AssetStorage<Texture>,
+        sheets: &AssetStorage<SpriteSheet>,
+    ) -> Option<SpriteSheetHandle> {
+        if let Some(handle) = self.sprite_sheet(name) {
+            return Some(handle);
+        }
+        let entry = self.tilesets.get(name)?;
+        log::info!("Loading tileset `{}`", name);
+        let texture_handle =
+            loader.load(entry.texture.as_str(), ImageFormat::default(), (), textures);
+        let handle = loader.load(
+            entry.sheet.as_str(),
+            SpriteSheetFormat(texture_handle),
+            (),
+            sheets,
+        );
+        self.sprite_sheets.insert(name.to_string(), handle.clone());
+        Some(handle)
+    }
+}
+
+/// Deserializes any YAML data file into `D`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct YamlFormat;
+
+impl<D> Format<D> for YamlFormat
+where
+    D: for<'a> Deserialize<'a> + Send + Sync + 'static,
+{
+    fn name(&self) -> &'static str {
+        "YAML"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<D> {
+        serde_yaml::from_slice(&bytes).map_err(amethyst::Error::new)
+    }
+}
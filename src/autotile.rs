@@ -0,0 +1,267 @@
+use amethyst::core::math::{Point2, Point3};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{
+    chunk::{tile_in_chunk, CHUNK_SIZE},
+    data::Terrain,
+    grid::Direction,
+    level::{Level, LevelTile},
+};
+
+/// Picks a tile's sprite from which of its neighbours it connects to, so
+/// fences and walls join up. Declared per tile in the terrain data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Autotile {
+    /// Tiles that count as connected, usually including the tile itself.
+    pub connects_to: Vec<LevelTile>,
+    /// Tried in order, the first match wins.
+    pub rules: Vec<AutotileRule>,
+}
+
+/// A neighbourhood pattern and the sprite to use when it matches.
+///
+/// The pattern has a character per neighbour: `#` must connect, `.` must
+/// not, and anything else doesn't matter. Four characters are the north,
+/// east, south and west neighbours; eight go clockwise from north, taking
+/// in the diagonals.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutotileRule {
+    pub pattern: String,
+    pub sprite: usize,
+}
+
+impl AutotileRule {
+    pub fn matches(&self, connected: &[bool; 8]) -> bool {
+        let neighbours: &[usize] = match self.pattern.chars().count() {
+            4 => &[0, 2, 4, 6],
+            8 => &[0, 1, 2, 3, 4, 5, 6, 7],
+            _ => return false,
+        };
+        self.pattern.chars().zip(neighbours).all(|(c, &i)| match c {
+            '#' => connected[i],
+            '.' => !connected[i],
+            _ => true,
+        })
+    }
+}
+
+impl Autotile {
+    /// Whether each neighbour of `p`, in `Direction::ALL` order, is a tile
+    /// this one connects to. Off the map never connects.
+    pub fn connections(&self, level: &Level, p: Point2<u32>) -> [bool; 8] {
+        let mut connected = [false; 8];
+        for (i, direction) in Direction::ALL.iter().enumerate() {
+            connected[i] = level
+                .neighbour(Point3::new(p.x, p.y, 0), *direction)
+                .and_then(|q| level.get_tile(q.xy()).ok())
+                .map(|tile| self.connects_to.contains(&tile))
+                .unwrap_or(false);
+        }
+        connected
+    }
+
+    pub fn sprite(&self, level: &Level, p: Point2<u32>) -> Option<usize> {
+        let connected = self.connections(level, p);
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(&connected))
+            .map(|rule| rule.sprite)
+    }
+}
+
+/// The sprites for the autotiled tiles of the chunks being drawn, worked
+/// out as each chunk is built since the neighbours don't change. Only the
+/// chunks near the camera are kept, however big the level.
+#[derive(Debug, Clone, Default)]
+pub struct Autotiles {
+    /// The rules for each kind of tile on the level that has any.
+    rules: HashMap<LevelTile, Autotile>,
+    /// Each drawn chunk's sprites, row by row.
+    chunks: HashMap<(i32, i32), Vec<Option<usize>>>,
+}
+
+impl Autotiles {
+    /// Picks out the rules for the kinds of tile on `level`, ready for its
+    /// chunks to be built.
+    pub fn new(level: &Level, terrain: &Terrain) -> Self {
+        let rules = level
+            .tile_kinds()
+            .into_iter()
+            .filter_map(|tile| Some((tile, terrain.autotile(tile)?.clone())))
+            .collect();
+        Self {
+            rules,
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// Works out the sprites for the drawn chunk `chunk`, unless they
+    /// already have been.
+    pub fn build_chunk(&mut self, level: &Level, chunk: (i32, i32)) {
+        if self.rules.is_empty() || self.chunks.contains_key(&chunk) {
+            return;
+        }
+        let mut sprites = vec![None; (CHUNK_SIZE * CHUNK_SIZE) as usize];
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let p = match tile_in_chunk(level, chunk, Point2::new(x, y)) {
+                    Some(p) => p,
+                    None => continue,
+                };
+                let autotile = level
+                    .get_tile(p)
+                    .ok()
+                    .and_then(|tile| self.rules.get(&tile));
+                if let Some(autotile) = autotile {
+                    sprites[(y * CHUNK_SIZE + x) as usize] = autotile.sprite(level, p);
+                }
+            }
+        }
+        self.chunks.insert(chunk, sprites);
+    }
+
+    /// Forgets the chunks that `keep` says are no longer drawn.
+    pub fn retain(&mut self, mut keep: impl FnMut((i32, i32)) -> bool) {
+        self.chunks.retain(|&chunk, _| keep(chunk));
+    }
+
+    /// The sprite for tile `p` of the drawn chunk `chunk`.
+    pub fn sprite(&self, chunk: (i32, i32), p: Point2<u32>) -> Option<usize> {
+        self.chunks
+            .get(&chunk)?
+            .get((p.y * CHUNK_SIZE + p.x) as usize)
+            .and_then(|sprite| *sprite)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(data: &str) -> Level {
+        Level::from_ascii(1, "Test", data).unwrap()
+    }
+
+    fn rule(pattern: &str, sprite: usize) -> AutotileRule {
+        AutotileRule {
+            pattern: pattern.to_string(),
+            sprite,
+        }
+    }
+
+    /// One sprite per combination of straight neighbours, numbered by the
+    /// bits north 1, east 2, south 4, west 8.
+    fn fence() -> Autotile {
+        let rules = (0..16)
+            .map(|mask| {
+                let pattern: String = [1, 2, 4, 8]
+                    .iter()
+                    .map(|bit| if mask & bit != 0 { '#' } else { '.' })
+                    .collect();
+                rule(&pattern, mask)
+            })
+            .collect();
+        Autotile {
+            connects_to: vec![LevelTile::Fence],
+            rules,
+        }
+    }
+
+    /// Rows of sprites for each tile of `data`, top row first like the
+    /// ASCII, `None` where nothing matched.
+    fn sprites(autotile: &Autotile, data: &str) -> Vec<Vec<Option<usize>>> {
+        let level = level(data);
+        (0..level.height as u32)
+            .rev()
+            .map(|y| {
+                (0..level.width as u32)
+                    .map(|x| {
+                        let p = Point2::new(x, y);
+                        match level.get_tile(p).unwrap() {
+                            LevelTile::Fence => autotile.sprite(&level, p),
+                            _ => None,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fence_pieces_join_up() {
+        let fence = fence();
+        assert_eq!(
+            sprites(&fence, "---\n-..\n-.."),
+            vec![
+                vec![Some(2 | 4), Some(2 | 8), Some(8)],
+                vec![Some(1 | 4), None, None],
+                vec![Some(1), None, None],
+            ]
+        );
+        // A lone post and a crossroads
+        assert_eq!(sprites(&fence, ".-.\n---\n.-.")[1][1], Some(15));
+        assert_eq!(sprites(&fence, "...\n.-.\n...")[1][1], Some(0));
+    }
+
+    #[test]
+    fn map_edges_do_not_connect() {
+        assert_eq!(sprites(&fence(), "-")[0][0], Some(0));
+        assert_eq!(sprites(&fence(), "--")[0], vec![Some(2), Some(8)]);
+    }
+
+    #[test]
+    fn eight_way_rules_see_diagonals() {
+        let wall = Autotile {
+            connects_to: vec![LevelTile::Fence],
+            rules: vec![rule("########", 1), rule("#?#?#?#?", 2), rule("????", 0)],
+        };
+        assert_eq!(sprites(&wall, "---\n---\n---")[1][1], Some(1));
+        assert_eq!(sprites(&wall, ".-.\n---\n.-.")[1][1], Some(2));
+        assert_eq!(sprites(&wall, ".-.\n.-.\n...")[1][1], Some(0));
+    }
+
+    #[test]
+    fn malformed_patterns_never_match() {
+        assert!(!rule("#.#", 0).matches(&[true; 8]));
+        assert!(rule("????", 0).matches(&[false; 8]));
+    }
+
+    fn terrain() -> Terrain {
+        let path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/data/terrain.yaml");
+        serde_yaml::from_reader(std::fs::File::open(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn build_uses_terrain_data() {
+        let terrain = terrain();
+        let level = level("#-\n##");
+        let mut autotiles = Autotiles::new(&level, &terrain);
+        assert_eq!(autotiles.sprite((0, 0), Point2::new(0, 0)), None);
+        autotiles.build_chunk(&level, (0, 0));
+        assert!(autotiles.sprite((0, 0), Point2::new(0, 0)).is_some());
+        assert!(autotiles.sprite((0, 0), Point2::new(1, 1)).is_some());
+        assert_ne!(
+            autotiles.sprite((0, 0), Point2::new(0, 0)),
+            autotiles.sprite((0, 0), Point2::new(1, 0))
+        );
+    }
+
+    #[test]
+    fn only_drawn_chunks_are_built() {
+        let terrain = terrain();
+        let level = Level::filled(1, "Rock", 16384, 16384, LevelTile::Wall);
+        let mut autotiles = Autotiles::new(&level, &terrain);
+        autotiles.build_chunk(&level, (100, 200));
+        autotiles.build_chunk(&level, (101, 200));
+        let inside = autotiles.sprite((100, 200), Point2::new(5, 5));
+        assert!(inside.is_some());
+        assert_eq!(autotiles.sprite((101, 200), Point2::new(5, 5)), inside);
+        assert_eq!(autotiles.sprite((0, 0), Point2::new(5, 5)), None);
+
+        autotiles.retain(|chunk| chunk == (101, 200));
+        assert_eq!(autotiles.sprite((100, 200), Point2::new(5, 5)), None);
+        assert_eq!(autotiles.sprite((101, 200), Point2::new(5, 5)), inside);
+    }
+}
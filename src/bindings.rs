@@ -0,0 +1,363 @@
+use amethyst::input::{Axis, BindingError, Bindings, Button, StringBindings};
+use std::fmt;
+
+/// One rebindable button: a side of an emulated axis, or a single-button
+/// action binding. The index picks which emulated pair or which binding
+/// when there are several.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BindingSlot {
+    AxisPositive(String, usize),
+    AxisNegative(String, usize),
+    Action(String, usize),
+}
+
+impl BindingSlot {
+    pub fn name(&self) -> &str {
+        match self {
+            BindingSlot::AxisPositive(name, _)
+            | BindingSlot::AxisNegative(name, _)
+            | BindingSlot::Action(name, _) => name,
+        }
+    }
+}
+
+impl fmt::Display for BindingSlot {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingSlot::AxisPositive(name, i) => write!(fmt, "{} + ({})", name, i + 1),
+            BindingSlot::AxisNegative(name, i) => write!(fmt, "{} - ({})", name, i + 1),
+            BindingSlot::Action(name, i) => write!(fmt, "{} ({})", name, i + 1),
+        }
+    }
+}
+
+/// Every slot in `bindings`, axes first, both sorted by name.
+pub fn slots(bindings: &Bindings<StringBindings>) -> Vec<BindingSlot> {
+    let mut axes: Vec<&String> = bindings.axes().collect();
+    axes.sort();
+    let mut actions: Vec<&String> = bindings.actions().collect();
+    actions.sort();
+
+    let mut slots = Vec::new();
+    for name in axes {
+        let pairs = bindings.axis(name).map(emulated_pairs).unwrap_or_default();
+        for (i, _) in pairs.iter().enumerate() {
+            slots.push(BindingSlot::AxisPositive(name.clone(), i));
+            slots.push(BindingSlot::AxisNegative(name.clone(), i));
+        }
+    }
+    for name in actions {
+        for (i, binding) in bindings.action_bindings(name).enumerate() {
+            if binding.len() == 1 {
+                slots.push(BindingSlot::Action(name.clone(), i));
+            }
+        }
+    }
+    slots
+}
+
+/// The button currently in `slot`.
+pub fn button(bindings: &Bindings<StringBindings>, slot: &BindingSlot) -> Option<Button> {
+    match slot {
+        BindingSlot::AxisPositive(name, i) => bindings
+            .axis(name)
+            .and_then(|axis| emulated_pairs(axis).get(*i).map(|(pos, _)| *pos)),
+        BindingSlot::AxisNegative(name, i) => bindings
+            .axis(name)
+            .and_then(|axis| emulated_pairs(axis).get(*i).map(|(_, neg)| *neg)),
+        BindingSlot::Action(name, i) => bindings
+            .action_bindings(name)
+            .nth(*i)
+            .and_then(|binding| binding.first().cloned()),
+    }
+}
+
+/// The slot other than `slot` that `button` is already bound to, if any.
+/// A button in a multi-button combo is reported as the combo's action and
+/// index, though the combo isn't a slot itself.
+pub fn conflict(
+    bindings: &Bindings<StringBindings>,
+    slot: &BindingSlot,
+    button: Button,
+) -> Option<BindingSlot> {
+    slots(bindings)
+        .into_iter()
+        .filter(|other| other != slot)
+        .find(|other| self::button(bindings, other) == Some(button))
+        .or_else(|| {
+            // Multi-button combos aren't slots, but still can't share buttons
+            // with an axis.
+            bindings.actions().find_map(|name| {
+                bindings
+                    .action_bindings(name)
+                    .position(|binding| binding.len() > 1 && binding.contains(&button))
+                    .map(|i| BindingSlot::Action(name.clone(), i))
+            })
+        })
+}
+
+/// `saved` over `defaults`. Saved axes and actions replace the defaults of
+/// the same name, and the rest keep their defaults, so actions added since
+/// the bindings were saved still work. Defaults using a button the saved
+/// bindings have taken are left out.
+pub fn overlay(
+    defaults: &Bindings<StringBindings>,
+    saved: &Bindings<StringBindings>,
+) -> Bindings<StringBindings> {
+    let mut bindings = saved.clone();
+    for name in defaults.axes() {
+        if let (None, Some(axis)) = (saved.axis(name), defaults.axis(name)) {
+            if let Err(e) = bindings.insert_axis(name.clone(), axis.clone()) {
+                log::warn!("Left out the default {} axis: {}", name, e);
+            }
+        }
+    }
+    for name in defaults.actions() {
+        if saved.action_bindings(name).next().is_some() {
+            continue;
+        }
+        for combo in defaults.action_bindings(name) {
+            if let Err(e) = bindings.insert_action_binding(name.clone(), combo.to_vec()) {
+                log::warn!("Left out a default {} binding: {}", name, e);
+            }
+        }
+    }
+    bindings
+}
+
+/// Puts `button` in `slot`. If another slot already uses `button`, the two
+/// swap so nothing is left unbound. A button held by a multi-button combo
+/// can't be swapped out of it, so is an error. On error `bindings` is left
+/// unchanged.
+pub fn rebind(
+    bindings: &mut Bindings<StringBindings>,
+    slot: &BindingSlot,
+    button: Button,
+) -> Result<(), BindingError<StringBindings>> {
+    let old = self::button(bindings, slot);
+    let other = conflict(bindings, slot, button);
+    if let Some(other) = &other {
+        if !slots(bindings).contains(other) {
+            return Err(BindingError::ComboAlreadyBound(other.name().to_string()));
+        }
+    }
+
+    let mut new_bindings = bindings.clone();
+    let mut entries = vec![Entry::take(&mut new_bindings, slot.name(), is_axis(slot))];
+    if let Some(other) = &other {
+        if other.name() != slot.name() || is_axis(other) != is_axis(slot) {
+            entries.push(Entry::take(&mut new_bindings, other.name(), is_axis(other)));
+        }
+    }
+
+    for entry in &mut entries {
+        entry.set(slot, button);
+        if let (Some(other), Some(old)) = (&other, old) {
+            entry.set(other, old);
+        }
+    }
+    for entry in entries {
+        entry.put(&mut new_bindings)?;
+    }
+
+    *bindings = new_bindings;
+    Ok(())
+}
+
+fn is_axis(slot: &BindingSlot) -> bool {
+    match slot {
+        BindingSlot::AxisPositive(..) | BindingSlot::AxisNegative(..) => true,
+        BindingSlot::Action(..) => false,
+    }
+}
+
+fn emulated_pairs(axis: &Axis) -> Vec<(Button, Button)> {
+    match axis {
+        Axis::Emulated { pos, neg } => vec![(*pos, *neg)],
+        Axis::Multiple(axes) => axes.iter().flat_map(emulated_pairs).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn emulated_pairs_mut(axis: &mut Axis) -> Vec<(&mut Button, &mut Button)> {
+    match axis {
+        Axis::Emulated { pos, neg } => vec![(pos, neg)],
+        Axis::Multiple(axes) => axes.iter_mut().flat_map(emulated_pairs_mut).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// An axis or action pulled out of `Bindings` so it can be edited without
+/// tripping the conflict checks on the way back in.
+enum Entry {
+    Axis(String, Option<Axis>),
+    Action(String, Vec<Vec<Button>>),
+}
+
+impl Entry {
+    fn take(bindings: &mut Bindings<StringBindings>, name: &str, axis: bool) -> Self {
+        if axis {
+            Entry::Axis(name.to_string(), bindings.remove_axis(name))
+        } else {
+            let combos: Vec<Vec<Button>> = bindings
+                .action_bindings(name)
+                .map(|binding| binding.to_vec())
+                .collect();
+            for combo in &combos {
+                bindings.remove_action_binding(name, combo);
+            }
+            Entry::Action(name.to_string(), combos)
+        }
+    }
+
+    fn set(&mut self, slot: &BindingSlot, button: Button) {
+        match (self, slot) {
+            (Entry::Axis(name, Some(axis)), BindingSlot::AxisPositive(slot_name, i))
+                if name == slot_name =>
+            {
+                if let Some((pos, _)) = emulated_pairs_mut(axis).into_iter().nth(*i) {
+                    *pos = button;
+                }
+            }
+            (Entry::Axis(name, Some(axis)), BindingSlot::AxisNegative(slot_name, i))
+                if name == slot_name =>
+            {
+                if let Some((_, neg)) = emulated_pairs_mut(axis).into_iter().nth(*i) {
+                    *neg = button;
+                }
+            }
+            (Entry::Action(name, combos), BindingSlot::Action(slot_name, i))
+                if name == slot_name =>
+            {
+                if let Some(combo) = combos.get_mut(*i) {
+                    *combo = vec![button];
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn put(
+        self,
+        bindings: &mut Bindings<StringBindings>,
+    ) -> Result<(), BindingError<StringBindings>> {
+        match self {
+            Entry::Axis(name, Some(axis)) => bindings.insert_axis(name, axis).map(|_| ()),
+            Entry::Axis(_, None) => Ok(()),
+            Entry::Action(name, combos) => {
+                for combo in combos {
+                    bindings.insert_action_binding(name.clone(), combo)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::input::VirtualKeyCode;
+
+    fn bindings() -> Bindings<StringBindings> {
+        let mut bindings = Bindings::new();
+        bindings
+            .insert_axis(
+                "east_west",
+                Axis::Emulated {
+                    pos: Button::Key(VirtualKeyCode::D),
+                    neg: Button::Key(VirtualKeyCode::A),
+                },
+            )
+            .unwrap();
+        bindings
+            .insert_action_binding("confirm".to_string(), vec![Button::Key(VirtualKeyCode::Return)])
+            .unwrap();
+        bindings
+            .insert_action_binding("cancel".to_string(), vec![Button::Key(VirtualKeyCode::X)])
+            .unwrap();
+        bindings
+    }
+
+    #[test]
+    fn rebind_unused_button() {
+        let mut bindings = bindings();
+        let slot = BindingSlot::Action("confirm".to_string(), 0);
+        let key = Button::Key(VirtualKeyCode::Z);
+        assert_eq!(conflict(&bindings, &slot, key), None);
+        rebind(&mut bindings, &slot, key).unwrap();
+        assert_eq!(button(&bindings, &slot), Some(key));
+    }
+
+    #[test]
+    fn conflicting_actions_swap() {
+        let mut bindings = bindings();
+        let confirm = BindingSlot::Action("confirm".to_string(), 0);
+        let cancel = BindingSlot::Action("cancel".to_string(), 0);
+        let x = Button::Key(VirtualKeyCode::X);
+        assert_eq!(conflict(&bindings, &confirm, x), Some(cancel.clone()));
+        rebind(&mut bindings, &confirm, x).unwrap();
+        assert_eq!(button(&bindings, &confirm), Some(x));
+        assert_eq!(
+            button(&bindings, &cancel),
+            Some(Button::Key(VirtualKeyCode::Return))
+        );
+    }
+
+    #[test]
+    fn axis_sides_swap() {
+        let mut bindings = bindings();
+        let east = BindingSlot::AxisPositive("east_west".to_string(), 0);
+        let west = BindingSlot::AxisNegative("east_west".to_string(), 0);
+        let a = Button::Key(VirtualKeyCode::A);
+        rebind(&mut bindings, &east, a).unwrap();
+        assert_eq!(button(&bindings, &east), Some(a));
+        assert_eq!(button(&bindings, &west), Some(Button::Key(VirtualKeyCode::D)));
+    }
+
+    #[test]
+    fn action_key_moves_to_axis() {
+        let mut bindings = bindings();
+        let east = BindingSlot::AxisPositive("east_west".to_string(), 0);
+        let confirm = BindingSlot::Action("confirm".to_string(), 0);
+        let enter = Button::Key(VirtualKeyCode::Return);
+        rebind(&mut bindings, &east, enter).unwrap();
+        assert_eq!(button(&bindings, &east), Some(enter));
+        assert_eq!(
+            button(&bindings, &confirm),
+            Some(Button::Key(VirtualKeyCode::D))
+        );
+    }
+
+    #[test]
+    fn buttons_in_combos_stay_put() {
+        let mut bindings = bindings();
+        let ctrl_m = vec![
+            Button::Key(VirtualKeyCode::LControl),
+            Button::Key(VirtualKeyCode::M),
+        ];
+        bindings
+            .insert_action_binding("menu".to_string(), vec![Button::Key(VirtualKeyCode::Tab)])
+            .unwrap();
+        bindings
+            .insert_action_binding("menu".to_string(), ctrl_m.clone())
+            .unwrap();
+
+        let cancel = BindingSlot::Action("cancel".to_string(), 0);
+        let m = Button::Key(VirtualKeyCode::M);
+        assert_eq!(
+            conflict(&bindings, &cancel, m),
+            Some(BindingSlot::Action("menu".to_string(), 1))
+        );
+        assert!(rebind(&mut bindings, &cancel, m).is_err());
+        assert_eq!(
+            button(&bindings, &cancel),
+            Some(Button::Key(VirtualKeyCode::X))
+        );
+        let menu: Vec<Vec<Button>> = bindings
+            .action_bindings("menu")
+            .map(|binding| binding.to_vec())
+            .collect();
+        assert_eq!(menu, vec![vec![Button::Key(VirtualKeyCode::Tab)], ctrl_m]);
+    }
+}
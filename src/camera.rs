@@ -0,0 +1,232 @@
+use amethyst::core::math::Vector2;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// How the camera follows the player, read from `resources/camera.ron`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    /// Roughly how many seconds the camera takes to catch up, 0 to snap.
+    pub smoothing: f32,
+    /// Half the width and height, in world units, of the box around the
+    /// middle of the screen the player can move in without the camera
+    /// following.
+    pub dead_zone: (f32, f32),
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            smoothing: 0.15,
+            dead_zone: (0.0, 0.0),
+        }
+    }
+}
+
+/// A scripted camera move to a tile, for cutscenes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraPan {
+    pub to: (u32, u32),
+    /// Seconds to get there.
+    pub secs: f32,
+    /// Seconds to stay there before the next pan, or before going back to
+    /// following the player.
+    pub hold_secs: f32,
+}
+
+/// Pans waiting to be played, in order. The camera follows the player
+/// whenever this is empty.
+#[derive(Debug, Clone, Default)]
+pub struct CameraScript {
+    pub pans: VecDeque<CameraPan>,
+}
+
+impl CameraScript {
+    pub fn pan_to(&mut self, to: (u32, u32), secs: f32, hold_secs: f32) {
+        self.pans.push_back(CameraPan {
+            to,
+            secs,
+            hold_secs,
+        });
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.pans.is_empty()
+    }
+}
+
+/// The camera position after `delta_seconds` of following `target`.
+pub fn follow(
+    camera: Vector2<f32>,
+    target: Vector2<f32>,
+    config: &CameraConfig,
+    delta_seconds: f32,
+) -> Vector2<f32> {
+    let offset = target - camera;
+    let outside = Vector2::new(
+        outside_dead_zone(offset.x, config.dead_zone.0),
+        outside_dead_zone(offset.y, config.dead_zone.1),
+    );
+    let t = if config.smoothing > 0.0 {
+        1.0 - (-delta_seconds / config.smoothing).exp()
+    } else {
+        1.0
+    };
+    camera + outside * t
+}
+
+fn outside_dead_zone(offset: f32, half: f32) -> f32 {
+    if offset > half {
+        offset - half
+    } else if offset < -half {
+        offset + half
+    } else {
+        0.0
+    }
+}
+
+/// Keeps a view of half size `half_view` inside `min`..`max`, centring it
+/// on any axis where the level is smaller than the view.
+pub fn clamp_to_bounds(
+    camera: Vector2<f32>,
+    half_view: Vector2<f32>,
+    min: Vector2<f32>,
+    max: Vector2<f32>,
+) -> Vector2<f32> {
+    let clamp = |c: f32, half: f32, min: f32, max: f32| {
+        if max - min <= half * 2.0 {
+            (min + max) / 2.0
+        } else {
+            c.max(min + half).min(max - half)
+        }
+    };
+    Vector2::new(
+        clamp(camera.x, half_view.x, min.x, max.x),
+        clamp(camera.y, half_view.y, min.y, max.y),
+    )
+}
+
+/// Of the drawings of `point` every `repeat` world units on a map that
+/// wraps, the one nearest `near`. Axes with no repeat are left alone.
+pub fn nearest_repeat(
+    point: Vector2<f32>,
+    near: Vector2<f32>,
+    repeat: Vector2<f32>,
+) -> Vector2<f32> {
+    let nearest = |p: f32, near: f32, repeat: f32| {
+        if repeat > 0.0 {
+            p + ((near - p) / repeat).round() * repeat
+        } else {
+            p
+        }
+    };
+    Vector2::new(
+        nearest(point.x, near.x, repeat.x),
+        nearest(point.y, near.y, repeat.y),
+    )
+}
+
+/// Where a pan from `from` to `to` is after `t` of the way through, easing
+/// in and out.
+pub fn pan_position(from: Vector2<f32>, to: Vector2<f32>, t: f32) -> Vector2<f32> {
+    let t = t.max(0.0).min(1.0);
+    from.lerp(&to, t * t * (3.0 - 2.0 * t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(x: f32, y: f32) -> Vector2<f32> {
+        Vector2::new(x, y)
+    }
+
+    fn snap(dead_zone: (f32, f32)) -> CameraConfig {
+        CameraConfig {
+            smoothing: 0.0,
+            dead_zone,
+        }
+    }
+
+    #[test]
+    fn snaps_without_smoothing() {
+        let config = snap((0.0, 0.0));
+        assert_eq!(
+            follow(v(0.0, 0.0), v(10.0, -5.0), &config, 0.016),
+            v(10.0, -5.0)
+        );
+    }
+
+    #[test]
+    fn dead_zone_holds_still() {
+        let config = snap((16.0, 8.0));
+        assert_eq!(
+            follow(v(0.0, 0.0), v(10.0, -5.0), &config, 0.016),
+            v(0.0, 0.0)
+        );
+        // Leaving the box drags the camera just far enough to keep up
+        assert_eq!(
+            follow(v(0.0, 0.0), v(20.0, -10.0), &config, 0.016),
+            v(4.0, -2.0)
+        );
+    }
+
+    #[test]
+    fn smoothing_closes_the_gap() {
+        let config = CameraConfig {
+            smoothing: 0.2,
+            dead_zone: (0.0, 0.0),
+        };
+        let mut camera = v(0.0, 0.0);
+        let mut last_gap = 100.0;
+        for _ in 0..60 {
+            camera = follow(camera, v(100.0, 0.0), &config, 1.0 / 60.0);
+            let gap = 100.0 - camera.x;
+            assert!(gap > 0.0 && gap < last_gap);
+            last_gap = gap;
+        }
+        assert!(last_gap < 1.0);
+    }
+
+    #[test]
+    fn clamps_to_level_edges() {
+        let (min, max) = (v(0.0, 0.0), v(640.0, 480.0));
+        let half_view = v(100.0, 100.0);
+        assert_eq!(
+            clamp_to_bounds(v(10.0, 300.0), half_view, min, max),
+            v(100.0, 300.0)
+        );
+        assert_eq!(
+            clamp_to_bounds(v(600.0, 470.0), half_view, min, max),
+            v(540.0, 380.0)
+        );
+        // Centred on a level smaller than the view
+        assert_eq!(
+            clamp_to_bounds(v(10.0, 10.0), v(400.0, 100.0), min, max),
+            v(320.0, 100.0)
+        );
+    }
+
+    #[test]
+    fn jumps_to_the_nearest_repeat() {
+        let repeat = v(640.0, 0.0);
+        // The player stepped off the east edge onto the west
+        assert_eq!(
+            nearest_repeat(v(300.0, 10.0), v(-310.0, 0.0), repeat),
+            v(-340.0, 10.0)
+        );
+        assert_eq!(
+            nearest_repeat(v(300.0, 10.0), v(290.0, 900.0), repeat),
+            v(300.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn pans_ease_between_points() {
+        let (from, to) = (v(0.0, 0.0), v(100.0, 50.0));
+        assert_eq!(pan_position(from, to, 0.0), from);
+        assert_eq!(pan_position(from, to, 0.5), v(50.0, 25.0));
+        assert_eq!(pan_position(from, to, 2.0), to);
+        assert!(pan_position(from, to, 0.1).x < 10.0);
+    }
+}
@@ -0,0 +1,473 @@
+use amethyst::core::math::{Point2, Point3, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    grid::Topology,
+    level::{Level, LevelTile},
+};
+
+/// Tiles along each side of a chunk, both in the `Level` store and in the
+/// tilemaps drawn near the camera.
+pub const CHUNK_SIZE: u32 = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Chunk {
+    /// Every tile in the chunk is the same.
+    Uniform(LevelTile),
+    /// Row by row, `CHUNK_SIZE` tiles each.
+    Tiles(Vec<LevelTile>),
+}
+
+/// A level's tiles, stored a chunk at a time. Chunks of a single kind of
+/// tile are kept as just that tile, and chunks that are all `fill` aren't
+/// kept at all, so open sea or empty space costs next to nothing.
+#[derive(Debug, Clone)]
+pub struct TileChunks {
+    fill: LevelTile,
+    chunks: HashMap<(u32, u32), Chunk>,
+}
+
+impl Default for TileChunks {
+    fn default() -> Self {
+        Self::new(LevelTile::Empty)
+    }
+}
+
+impl TileChunks {
+    pub fn new(fill: LevelTile) -> Self {
+        Self {
+            fill,
+            chunks: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> LevelTile {
+        match self.chunks.get(&chunk_of(x, y)) {
+            Some(Chunk::Uniform(tile)) => *tile,
+            Some(Chunk::Tiles(tiles)) => tiles[index_in_chunk(x, y)],
+            None => self.fill,
+        }
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, tile: LevelTile) {
+        let fill = self.fill;
+        let chunk = self
+            .chunks
+            .entry(chunk_of(x, y))
+            .or_insert(Chunk::Uniform(fill));
+        if let Chunk::Uniform(uniform) = *chunk {
+            if uniform == tile {
+                return;
+            }
+            *chunk = Chunk::Tiles(vec![uniform; (CHUNK_SIZE * CHUNK_SIZE) as usize]);
+        }
+        if let Chunk::Tiles(tiles) = chunk {
+            tiles[index_in_chunk(x, y)] = tile;
+        }
+    }
+
+    /// Shrinks chunks that have ended up all one tile, and drops those that
+    /// are all `fill`.
+    pub fn compact(&mut self) {
+        for chunk in self.chunks.values_mut() {
+            if let Chunk::Tiles(tiles) = chunk {
+                let first = tiles[0];
+                if tiles.iter().all(|&tile| tile == first) {
+                    *chunk = Chunk::Uniform(first);
+                }
+            }
+        }
+        let fill = self.fill;
+        self.chunks
+            .retain(|_, chunk| *chunk != Chunk::Uniform(fill));
+        self.chunks.shrink_to_fit();
+    }
+
+    /// Every kind of tile stored, counting `fill`.
+    pub fn kinds(&self) -> HashSet<LevelTile> {
+        let mut kinds = HashSet::new();
+        kinds.insert(self.fill);
+        for chunk in self.chunks.values() {
+            match chunk {
+                Chunk::Uniform(tile) => {
+                    kinds.insert(*tile);
+                }
+                Chunk::Tiles(tiles) => kinds.extend(tiles.iter().cloned()),
+            }
+        }
+        kinds
+    }
+
+    /// Roughly how many bytes the store has allocated.
+    pub fn heap_bytes(&self) -> usize {
+        let tiles: usize = self
+            .chunks
+            .values()
+            .map(|chunk| match chunk {
+                Chunk::Uniform(_) => 0,
+                Chunk::Tiles(tiles) => tiles.capacity() * std::mem::size_of::<LevelTile>(),
+            })
+            .sum();
+        let entries = self.chunks.capacity() * std::mem::size_of::<((u32, u32), Chunk)>();
+        tiles + entries
+    }
+}
+
+/// Bits per word of a `TileSet` chunk.
+const WORD_BITS: u32 = 64;
+
+/// Some of a level's tiles, stored a chunk at a time as bits. Chunks with
+/// none of the tiles in aren't kept, so it costs nothing for the parts of a
+/// level it doesn't reach.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TileSet {
+    chunks: HashMap<(u32, u32), Vec<u64>>,
+}
+
+impl TileSet {
+    pub fn contains(&self, p: Point2<u32>) -> bool {
+        let i = index_in_chunk(p.x, p.y) as u32;
+        self.chunks.get(&chunk_of(p.x, p.y)).map_or(false, |words| {
+            words[(i / WORD_BITS) as usize] & (1 << (i % WORD_BITS)) != 0
+        })
+    }
+
+    /// Adds `p`, returning whether it wasn't already in the set.
+    pub fn insert(&mut self, p: Point2<u32>) -> bool {
+        let i = index_in_chunk(p.x, p.y) as u32;
+        let words = self
+            .chunks
+            .entry(chunk_of(p.x, p.y))
+            .or_insert_with(|| vec![0; (CHUNK_SIZE * CHUNK_SIZE / WORD_BITS) as usize]);
+        let (word, bit) = (&mut words[(i / WORD_BITS) as usize], 1 << (i % WORD_BITS));
+        let added = *word & bit == 0;
+        *word |= bit;
+        added
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Every tile in the set, a chunk at a time.
+    pub fn iter(&self) -> impl Iterator<Item = Point2<u32>> + '_ {
+        self.chunks.iter().flat_map(|(&(cx, cy), words)| {
+            words.iter().enumerate().flat_map(move |(w, &word)| {
+                (0..WORD_BITS)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| {
+                        let i = w as u32 * WORD_BITS + bit;
+                        Point2::new(
+                            cx * CHUNK_SIZE + i % CHUNK_SIZE,
+                            cy * CHUNK_SIZE + i / CHUNK_SIZE,
+                        )
+                    })
+            })
+        })
+    }
+
+    /// Roughly how many bytes the set has allocated.
+    pub fn heap_bytes(&self) -> usize {
+        let words: usize = self
+            .chunks
+            .values()
+            .map(|words| words.capacity() * std::mem::size_of::<u64>())
+            .sum();
+        words + self.chunks.capacity() * std::mem::size_of::<((u32, u32), Vec<u64>)>()
+    }
+}
+
+/// The chunk holding tile `(x, y)`.
+pub fn chunk_of(x: u32, y: u32) -> (u32, u32) {
+    (x / CHUNK_SIZE, y / CHUNK_SIZE)
+}
+
+fn index_in_chunk(x: u32, y: u32) -> usize {
+    ((y % CHUNK_SIZE) * CHUNK_SIZE + x % CHUNK_SIZE) as usize
+}
+
+/// Where tile `p` of the drawn chunk `chunk` is on `level`. Past the edges
+/// of levels that wrap, chunks repeat the other side.
+pub fn tile_in_chunk(level: &Level, chunk: (i32, i32), p: Point2<u32>) -> Option<Point2<u32>> {
+    let size = i64::from(CHUNK_SIZE);
+    level.wrap(
+        i64::from(chunk.0) * size + i64::from(p.x),
+        i64::from(chunk.1) * size + i64::from(p.y),
+    )
+}
+
+/// Converts between tile and world coordinates over the whole level, the
+/// same way a single `TileMap` covering it would, now that the map is only
+/// ever drawn a few chunks at a time. On levels that wrap, the map repeats
+/// endlessly past the edges that join.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapGrid {
+    dimensions: Vector3<u32>,
+    tile_dimensions: Vector3<u32>,
+    topology: Topology,
+}
+
+impl MapGrid {
+    pub fn new(dimensions: Vector3<u32>, tile_dimensions: Vector3<u32>) -> Self {
+        Self {
+            dimensions,
+            tile_dimensions,
+            topology: Topology::default(),
+        }
+    }
+
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn dimensions(&self) -> &Vector3<u32> {
+        &self.dimensions
+    }
+
+    pub fn tile_dimensions(&self) -> &Vector3<u32> {
+        &self.tile_dimensions
+    }
+
+    /// The middle of tile `coord`. The map is centred on the origin, with
+    /// tile rows going down the screen.
+    pub fn to_world(&self, coord: &Point3<u32>) -> Vector3<f32> {
+        let (d, t) = (self.dimensions, self.tile_dimensions);
+        Vector3::new(
+            (coord.x as f32 - d.x as f32 / 2.0) * t.x as f32,
+            (d.y as f32 / 2.0 - coord.y as f32) * t.y as f32,
+            (coord.z as f32 - d.z as f32 / 2.0) * t.z as f32,
+        )
+    }
+
+    /// Where tile `to` is drawn, seen from tile `from` drawn at `at`: the
+    /// short way round on maps that wrap, even if that's past the edge.
+    pub fn to_world_from(
+        &self,
+        at: Vector3<f32>,
+        from: &Point3<u32>,
+        to: &Point3<u32>,
+    ) -> Vector3<f32> {
+        let offset = self.offset(from, to);
+        let t = self.tile_dimensions;
+        at + Vector3::new(
+            offset.x as f32 * t.x as f32,
+            -offset.y as f32 * t.y as f32,
+            0.0,
+        )
+    }
+
+    /// The shortest offset in tiles from `from` to `to`.
+    pub fn offset(&self, from: &Point3<u32>, to: &Point3<u32>) -> Vector2<i64> {
+        let d = self.dimensions;
+        self.topology.offset(from.xy(), to.xy(), d.x, d.y)
+    }
+
+    /// The tile under world point `point`, if it's on the map or one of its
+    /// repeats.
+    pub fn to_tile(&self, point: &Vector3<f32>) -> Option<Point3<u32>> {
+        let (x, y) = self.unwrapped_tile(point.xy());
+        let d = self.dimensions;
+        let p = self.topology.wrap(x, y, d.x, d.y)?;
+        Some(Point3::new(p.x, p.y, 0))
+    }
+
+    /// The tile coordinates under world point `point`, counting on past the
+    /// edges of the map.
+    fn unwrapped_tile(&self, point: Vector2<f32>) -> (i64, i64) {
+        let (d, t) = (self.dimensions, self.tile_dimensions);
+        (
+            (point.x / t.x as f32 + d.x as f32 / 2.0).round() as i64,
+            (d.y as f32 / 2.0 - point.y / t.y as f32).round() as i64,
+        )
+    }
+
+    /// How far apart two drawings of the same tile are on maps that wrap,
+    /// zero along edges that don't.
+    pub fn repeat(&self) -> Vector2<f32> {
+        let (d, t) = (self.dimensions, self.tile_dimensions);
+        let along = |wraps: bool, size: u32, tile: u32| {
+            if wraps {
+                (size * tile) as f32
+            } else {
+                0.0
+            }
+        };
+        Vector2::new(
+            along(self.topology.wraps_x(), d.x, t.x),
+            along(self.topology.wraps_y(), d.y, t.y),
+        )
+    }
+
+    /// Where the first tile of `chunk` is drawn.
+    pub fn chunk_origin(&self, chunk: (i32, i32)) -> Vector3<f32> {
+        let (d, t) = (self.dimensions, self.tile_dimensions);
+        let (x, y) = (
+            i64::from(chunk.0) * i64::from(CHUNK_SIZE),
+            i64::from(chunk.1) * i64::from(CHUNK_SIZE),
+        );
+        Vector3::new(
+            (x as f32 - d.x as f32 / 2.0) * t.x as f32,
+            (d.y as f32 / 2.0 - y as f32) * t.y as f32,
+            -(d.z as f32 / 2.0) * t.z as f32,
+        )
+    }
+
+    /// The chunks with any tile within `half_extent` world units of
+    /// `centre`. Counted on past edges that wrap, where they repeat the
+    /// chunks at the other side, and clipped to edges that don't.
+    pub fn chunks_near(&self, centre: Vector2<f32>, half_extent: Vector2<f32>) -> Vec<(i32, i32)> {
+        let d = self.dimensions;
+        if d.x == 0 || d.y == 0 {
+            return Vec::new();
+        }
+        let (x0, y1) = self.unwrapped_tile(centre - half_extent);
+        let (x1, y0) = self.unwrapped_tile(centre + half_extent);
+        let range = |from: i64, to: i64, size: u32, wraps: bool| {
+            let (from, to) = if wraps {
+                (from, to)
+            } else {
+                let last = i64::from(size) - 1;
+                (from.max(0).min(last), to.max(0).min(last))
+            };
+            let chunk = i64::from(CHUNK_SIZE);
+            from.div_euclid(chunk) as i32..=to.div_euclid(chunk) as i32
+        };
+        let mut chunks = Vec::new();
+        for cy in range(y0, y1, d.y, self.topology.wraps_y()) {
+            for cx in range(x0, x1, d.x, self.topology.wraps_x()) {
+                chunks.push((cx, cy));
+            }
+        }
+        chunks
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::level::Level;
+    use amethyst::core::math::Point2;
+
+    /// A `size` square sea with the same few islands near its corner
+    /// however big it is.
+    pub fn islands(size: usize) -> Level {
+        let mut level = Level::filled(1, "Islands", size, size, LevelTile::Water);
+        for &(x, y) in &[(10, 10), (60, 20), (30, 90), (120, 140)] {
+            for dx in 0..24 {
+                for dy in 0..16 {
+                    let tile = if (dx + dy) % 5 == 0 {
+                        LevelTile::Plain
+                    } else {
+                        LevelTile::Grass
+                    };
+                    level.set_tile(Point2::new(x + dx, y + dy), tile);
+                }
+            }
+        }
+        level
+    }
+
+    #[test]
+    fn chunks_store_and_compact() {
+        let mut tiles = TileChunks::new(LevelTile::Water);
+        assert_eq!(tiles.get(1000, 1000), LevelTile::Water);
+        tiles.set(40, 3, LevelTile::Grass);
+        tiles.set(41, 3, LevelTile::Grass);
+        assert_eq!(tiles.get(40, 3), LevelTile::Grass);
+        assert_eq!(tiles.get(42, 3), LevelTile::Water);
+        assert_eq!(tiles.chunks.len(), 1);
+
+        tiles.set(40, 3, LevelTile::Water);
+        tiles.set(41, 3, LevelTile::Water);
+        tiles.compact();
+        assert!(tiles.chunks.is_empty());
+        assert_eq!(tiles.heap_bytes(), 0);
+        assert_eq!(tiles.get(41, 3), LevelTile::Water);
+    }
+
+    #[test]
+    fn tile_kinds() {
+        let mut tiles = TileChunks::default();
+        tiles.set(0, 0, LevelTile::Wall);
+        tiles.set(100, 0, LevelTile::Lava);
+        let kinds = tiles.kinds();
+        assert_eq!(kinds.len(), 3);
+        assert!(kinds.contains(&LevelTile::Empty));
+        assert!(kinds.contains(&LevelTile::Lava));
+    }
+
+    #[test]
+    fn continents_are_sparse() {
+        // An island in the middle of a 4096 tile square sea
+        let mut tiles = TileChunks::new(LevelTile::Water);
+        for x in 2000..2100 {
+            for y in 2000..2100 {
+                tiles.set(x, y, LevelTile::Grass);
+            }
+        }
+        tiles.compact();
+        assert!(tiles.heap_bytes() < 64 * 1024);
+    }
+
+    #[test]
+    fn grid_round_trips() {
+        let grid = MapGrid::new(Vector3::new(10, 6, 1), Vector3::new(32, 32, 1));
+        for &(x, y) in &[(0, 0), (9, 5), (3, 4)] {
+            let p = Point3::new(x, y, 0);
+            assert_eq!(grid.to_tile(&grid.to_world(&p)), Some(p));
+        }
+        assert_eq!(
+            grid.to_world(&Point3::new(0, 0, 0)).xy(),
+            Vector2::new(-160.0, 96.0)
+        );
+        assert_eq!(grid.to_tile(&Vector3::new(-180.0, 0.0, 0.0)), None);
+        assert_eq!(grid.to_tile(&Vector3::new(0.0, 120.0, 0.0)), None);
+    }
+
+    #[test]
+    fn chunks_near_the_camera() {
+        let grid = MapGrid::new(Vector3::new(200, 100, 1), Vector3::new(32, 32, 1));
+        // Around the middle, a view a chunk and a half across
+        let middle = grid.to_world(&Point3::new(100, 50, 0)).xy();
+        let chunks = grid.chunks_near(middle, Vector2::new(24.0 * 32.0, 8.0 * 32.0));
+        assert_eq!(chunks, vec![(2, 1), (3, 1)]);
+        // Off the corner, only the corner chunk on a bounded map, and the
+        // chunks repeating the other sides on one that wraps
+        let corner = grid.to_world(&Point3::new(0, 0, 0)).xy() - Vector2::new(500.0, -500.0);
+        assert_eq!(
+            grid.chunks_near(corner, Vector2::new(100.0, 100.0)),
+            vec![(0, 0)]
+        );
+        let world = grid.clone().with_topology(Topology::WrapBoth);
+        assert_eq!(
+            world.chunks_near(corner, Vector2::new(100.0, 100.0)),
+            vec![(-1, -1)]
+        );
+        let west = grid.to_world(&Point3::new(0, 50, 0)).xy();
+        assert_eq!(
+            world.chunks_near(west, Vector2::new(32.0, 32.0)),
+            vec![(-1, 1), (0, 1)]
+        );
+    }
+
+    #[test]
+    fn wrapped_grids_repeat() {
+        let grid = MapGrid::new(Vector3::new(10, 6, 1), Vector3::new(32, 32, 1))
+            .with_topology(Topology::WrapHorizontal);
+        assert_eq!(grid.repeat(), Vector2::new(320.0, 0.0));
+        let east = grid.to_world(&Point3::new(9, 2, 0));
+        let past_east = east + Vector3::new(32.0, 0.0, 0.0);
+        assert_eq!(grid.to_tile(&past_east), Some(Point3::new(0, 2, 0)));
+        assert_eq!(
+            grid.to_world_from(east, &Point3::new(9, 2, 0), &Point3::new(0, 2, 0)),
+            past_east
+        );
+        assert_eq!(grid.to_tile(&(east + Vector3::new(0.0, 200.0, 0.0))), None);
+    }
+}
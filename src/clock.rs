@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use crate::component::NpcGoal;
+
+pub const MINUTES_PER_DAY: f32 = 24.0 * 60.0;
+
+/// How fast time passes in the game world, read from `resources/clock.ron`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClockConfig {
+    /// Game minutes passing each real second.
+    pub minutes_per_second: f32,
+    /// Game minutes passing each tile the player walks.
+    pub minutes_per_step: f32,
+    /// The hour a new game starts at.
+    pub start_hour: f32,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            minutes_per_second: 1.0,
+            minutes_per_step: 1.0,
+            start_hour: 8.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TimeOfDay {
+    /// 05:00 to 07:00.
+    Dawn,
+    Day,
+    /// 18:00 to 20:00.
+    Dusk,
+    Night,
+}
+
+/// The time in the game world, kept as a resource and in saves.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub struct WorldClock {
+    /// Days since the game started.
+    pub day: u32,
+    /// Minutes since midnight.
+    pub minute: f32,
+}
+
+impl WorldClock {
+    pub fn at_hour(hour: f32) -> Self {
+        let mut clock = Self::default();
+        clock.advance(hour * 60.0);
+        clock
+    }
+
+    pub fn advance(&mut self, minutes: f32) {
+        let total = self.minute + minutes.max(0.0);
+        self.day += (total / MINUTES_PER_DAY) as u32;
+        self.minute = total % MINUTES_PER_DAY;
+    }
+
+    /// Hours since midnight, with the minutes as a fraction.
+    pub fn hour(&self) -> f32 {
+        self.minute / 60.0
+    }
+
+    pub fn time_of_day(&self) -> TimeOfDay {
+        match self.hour() {
+            h if h < 5.0 => TimeOfDay::Night,
+            h if h < 7.0 => TimeOfDay::Dawn,
+            h if h < 18.0 => TimeOfDay::Day,
+            h if h < 20.0 => TimeOfDay::Dusk,
+            _ => TimeOfDay::Night,
+        }
+    }
+
+    /// How much of the sun is out, from 0 at night to 1 by day, rising
+    /// through dawn and falling through dusk.
+    pub fn daylight(&self) -> f32 {
+        let hour = self.hour();
+        match self.time_of_day() {
+            TimeOfDay::Night => 0.0,
+            TimeOfDay::Dawn => (hour - 5.0) / 2.0,
+            TimeOfDay::Day => 1.0,
+            TimeOfDay::Dusk => 1.0 - (hour - 18.0) / 2.0,
+        }
+    }
+}
+
+/// What an NPC does from `from` o'clock until the next entry starts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    pub from: f32,
+    pub goal: NpcGoal,
+}
+
+/// The goal of the entry that started most recently at `hour`, carrying on
+/// from the previous day's last entry before the first one starts.
+pub fn scheduled_goal(schedule: &[ScheduleEntry], hour: f32) -> Option<&NpcGoal> {
+    let latest = |a: &&ScheduleEntry, b: &&ScheduleEntry| {
+        a.from.partial_cmp(&b.from).unwrap_or(Ordering::Equal)
+    };
+    schedule
+        .iter()
+        .filter(|entry| entry.from <= hour)
+        .max_by(latest)
+        .or_else(|| schedule.iter().max_by(latest))
+        .map(|entry| &entry.goal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_rolls_over_days() {
+        let mut clock = WorldClock::at_hour(23.0);
+        assert_eq!(clock.day, 0);
+        clock.advance(90.0);
+        assert_eq!(clock.day, 1);
+        assert!((clock.hour() - 0.5).abs() < 0.001);
+        clock.advance(3.0 * MINUTES_PER_DAY);
+        assert_eq!(clock.day, 4);
+    }
+
+    #[test]
+    fn daylight_follows_the_sun() {
+        let at = |hour| WorldClock::at_hour(hour);
+        assert_eq!(at(2.0).time_of_day(), TimeOfDay::Night);
+        assert_eq!(at(6.0).time_of_day(), TimeOfDay::Dawn);
+        assert_eq!(at(12.0).time_of_day(), TimeOfDay::Day);
+        assert_eq!(at(19.0).time_of_day(), TimeOfDay::Dusk);
+        assert_eq!(at(22.0).time_of_day(), TimeOfDay::Night);
+
+        assert_eq!(at(2.0).daylight(), 0.0);
+        assert!((at(6.0).daylight() - 0.5).abs() < 0.001);
+        assert_eq!(at(12.0).daylight(), 1.0);
+        assert!((at(19.5).daylight() - 0.25).abs() < 0.001);
+    }
+
+    #[test]
+    fn schedules_wrap_round_midnight() {
+        let schedule = vec![
+            ScheduleEntry {
+                from: 8.0,
+                goal: NpcGoal::WalkTo((1, 1)),
+            },
+            ScheduleEntry {
+                from: 20.0,
+                goal: NpcGoal::WalkTo((5, 5)),
+            },
+        ];
+        assert_eq!(
+            scheduled_goal(&schedule, 12.0),
+            Some(&NpcGoal::WalkTo((1, 1)))
+        );
+        assert_eq!(
+            scheduled_goal(&schedule, 21.0),
+            Some(&NpcGoal::WalkTo((5, 5)))
+        );
+        assert_eq!(
+            scheduled_goal(&schedule, 3.0),
+            Some(&NpcGoal::WalkTo((5, 5)))
+        );
+        assert_eq!(scheduled_goal(&[], 3.0), None);
+    }
+}
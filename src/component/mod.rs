@@ -1,13 +1,15 @@
 use amethyst::{
-    core::{math::{Point3, Vector3}},
+    core::math::{Point3, Vector3},
     ecs::prelude::{Component, DenseVecStorage, NullStorage},
-    tiles::Map,
 };
 
-use minterpolate::{linear_interpolate, InterpolationPrimitive};
-use std::{ops::Add, time::Duration};
+use minterpolate::{
+    catmull_rom_spline_interpolate, linear_interpolate, step_interpolate, InterpolationPrimitive,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, time::Duration};
 
-use crate::states::game::TileMap;
+use crate::{animation::CharacterSheet, chunk::MapGrid, clock::ScheduleEntry, grid::Direction};
 
 #[derive(Debug, Default)]
 pub struct Player;
@@ -16,50 +18,149 @@ impl Component for Player {
     type Storage = NullStorage<Self>;
 }
 
+/// How an entity's speed changes over the course of a move.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Easing {
+    Linear,
+    /// Accelerates away from the start and slows into the end of the path.
+    EaseInOut,
+    /// A smooth curve through every waypoint.
+    CubicSpline,
+    /// Jumps from waypoint to waypoint.
+    Step,
+}
+
+impl Default for Easing {
+    fn default() -> Self {
+        Easing::Linear
+    }
+}
+
+/// Moves an entity through one or more tiles. Each waypoint takes the step
+/// duration scaled by its distance from the previous one, so diagonals
+/// aren't faster.
 #[derive(Debug)]
 pub struct MovingObject {
     start_time: f64,
-    duration: Duration,
-    start: Vector3<f32>,
-    end: Vector3<f32>,
+    easing: Easing,
+    /// Seconds after `start_time` each point is reached, starting at 0.
+    times: Vec<f32>,
+    /// Where each point is drawn, carrying on past the edge of maps that
+    /// wrap rather than crossing the whole map.
+    points: Vec<Vec3>,
+    positions: Vec<Position>,
+    /// Where `end_p` is drawn, back on the map.
+    rest: Vec3,
     pub end_p: Position,
 }
 
 impl MovingObject {
     pub fn new(
         start_time: f64,
-        duration: Duration,
-        tilemap: &TileMap,
+        step_duration: Duration,
+        grid: &MapGrid,
         s: Position,
         e: Position,
     ) -> Self {
-        let start = tilemap.to_world(&s.0, None);
-        let end = tilemap.to_world(&e.0, None);
+        Self::path(start_time, step_duration, grid, s, &[e])
+    }
+
+    /// A move from `s` through each of `waypoints` in turn.
+    pub fn path(
+        start_time: f64,
+        step_duration: Duration,
+        grid: &MapGrid,
+        s: Position,
+        waypoints: &[Position],
+    ) -> Self {
+        let mut times = vec![0.0];
+        let mut points = vec![Vec3(grid.to_world(&s.0))];
+        let mut positions = vec![s];
+        let mut previous = s;
+        for &p in waypoints {
+            let offset = grid.offset(&previous.0, &p.0);
+            let (dx, dy) = (offset.x as f64, offset.y as f64);
+            let duration = step_duration.mul_f64(dx.hypot(dy).max(1.0));
+            times.push(times[times.len() - 1] + duration.as_secs_f32());
+            let from = points[points.len() - 1].0;
+            points.push(Vec3(grid.to_world_from(from, &previous.0, &p.0)));
+            positions.push(p);
+            previous = p;
+        }
         Self {
             start_time,
-            duration,
-            start,
-            end,
-            end_p: e,
+            easing: Easing::Linear,
+            times,
+            points,
+            positions,
+            rest: Vec3(grid.to_world(&previous.0)),
+            end_p: previous,
         }
     }
 
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
     pub fn interpolate(&self, now: f64) -> Vector3<f32> {
-        if !self.is_done(now) {
-            linear_interpolate(
-                (now - self.start_time) as f32,
-                &[0.0, self.duration.as_secs_f32()],
-                &[Vec(self.start), Vec(self.end)],
-                false,
-            )
-            .0
-        } else {
-            self.end
+        if self.is_done(now) {
+            return self.rest.0;
         }
+        let t = ((now - self.start_time) as f32).max(0.0);
+        match self.easing {
+            Easing::Linear => linear_interpolate(t, &self.times, &self.points, false).0,
+            Easing::EaseInOut => {
+                let total = self.total_secs();
+                let x = t / total;
+                let eased = total * x * x * (3.0 - 2.0 * x);
+                linear_interpolate(eased, &self.times, &self.points, false).0
+            }
+            Easing::CubicSpline => {
+                // Zero in and out tangents, so the path starts and ends at rest
+                let rest = Vec3(Vector3::zeros());
+                let mut outputs = Vec::with_capacity(self.points.len() + 2);
+                outputs.push(rest);
+                outputs.extend_from_slice(&self.points);
+                outputs.push(rest);
+                catmull_rom_spline_interpolate(t, &self.times, &outputs, false).0
+            }
+            Easing::Step => step_interpolate(t, &self.times, &self.points, false).0,
+        }
+    }
+
+    /// The waypoints either side of `now` and how far the move is from one
+    /// to the other, or `None` once it has finished.
+    pub fn segment_at(&self, now: f64) -> Option<(Position, Position, f32)> {
+        if self.is_done(now) {
+            return None;
+        }
+        let t = ((now - self.start_time) as f32).max(0.0);
+        let passed = self.times.iter().take_while(|&&time| time <= t).count();
+        let i = passed.max(1).min(self.times.len() - 1);
+        let (from, to) = (self.times[i - 1], self.times[i]);
+        let progress = if to > from { (t - from) / (to - from) } else { 1.0 };
+        Some((self.positions[i - 1], self.positions[i], progress.min(1.0)))
+    }
+
+    /// The last waypoint passed at `now`.
+    pub fn position_at(&self, now: f64) -> Position {
+        let t = (now - self.start_time) as f32;
+        let passed = self.times.iter().take_while(|&&time| time <= t).count();
+        self.positions[passed.max(1) - 1]
     }
 
     pub fn is_done(&self, now: f64) -> bool {
-        self.start_time + self.duration.as_secs_f64() < now
+        self.end_time() < now
+    }
+
+    /// When the move finishes, which is when a chained move should start.
+    pub fn end_time(&self) -> f64 {
+        self.start_time + f64::from(self.total_secs())
+    }
+
+    fn total_secs(&self) -> f32 {
+        self.times[self.times.len() - 1]
     }
 }
 
@@ -67,29 +168,161 @@ impl Component for MovingObject {
     type Storage = DenseVecStorage<Self>;
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Position(pub Point3<u32>);
 
 impl Component for Position {
     type Storage = DenseVecStorage<Self>;
 }
 
-impl Add for Position {
-    type Output = Self;
+impl Position {
+    /// The position one tile away in `direction`, or `None` past the origin.
+    pub fn step(self, direction: Direction) -> Option<Self> {
+        direction.step_from(self.0).map(Position)
+    }
+}
 
-    fn add(self, other: Self) -> Self::Output {
-        Position(Point3::new(
-            self.0.x + other.0.x,
-            self.0.y + other.0.y,
-            self.0.z + other.0.z,
-        ))
+/// Tiles still to walk through, one step at a time. Removed once the last
+/// step starts or the next tile turns out to be blocked.
+#[derive(Debug, Clone, Default)]
+pub struct FollowPath {
+    pub steps: VecDeque<Position>,
+}
+
+impl Component for FollowPath {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// What a non-player character is trying to do.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NpcGoal {
+    /// Stands still.
+    Idle,
+    /// Walks to a tile and stays there.
+    WalkTo((u32, u32)),
+    /// Keeps walking towards the player.
+    ChasePlayer,
+}
+
+impl Default for NpcGoal {
+    fn default() -> Self {
+        NpcGoal::Idle
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Npc {
+    pub name: String,
+    pub goal: NpcGoal,
+    /// Goals by time of day, taking over from `goal` when there are any.
+    pub schedule: Vec<ScheduleEntry>,
+}
+
+impl Component for Npc {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Which way a character is looking.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Facing(pub Direction);
+
+impl Default for Facing {
+    fn default() -> Self {
+        Facing(Direction::South)
     }
 }
 
+impl Component for Facing {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Picks frames from a character sheet for the entity's `Facing` and
+/// movement.
+#[derive(Debug, Clone, Default)]
+pub struct CharacterSprite {
+    pub sheet: CharacterSheet,
+    /// Steps started so far, to alternate feet.
+    pub strides: u32,
+    /// The start of the step being animated.
+    pub step_from: Option<Position>,
+}
+
+impl Component for CharacterSprite {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// A block the player can push one tile at a time.
+#[derive(Debug, Clone)]
+pub struct Pushable {
+    /// Where the block goes back to when the room is reset.
+    pub home: Position,
+}
+
+impl Component for Pushable {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Sets `flag` while a block rests on the plate. Latching plates keep it set
+/// once it has been pressed.
+#[derive(Debug, Clone)]
+pub struct PressurePlate {
+    pub at: Position,
+    pub flag: String,
+    pub latch: bool,
+}
+
+impl Component for PressurePlate {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Takes the party down to the next dungeon floor when stepped onto, if
+/// `down`.
+#[derive(Debug, Clone)]
+pub struct Stairs {
+    pub at: Position,
+    pub down: bool,
+}
+
+impl Component for Stairs {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Spawned from one of the level's markers, so it can be spawned again when
+/// the level is reloaded.
+#[derive(Debug, Default)]
+pub struct FromMarker;
+
+impl Component for FromMarker {
+    type Storage = NullStorage<Self>;
+}
+
+/// Gives the party `item` when stepped onto, and is gone once opened.
+#[derive(Debug, Clone)]
+pub struct Chest {
+    pub at: Position,
+    pub item: Option<String>,
+}
+
+impl Component for Chest {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Lights up the tiles around the entity.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LightSource {
+    /// Tiles from the entity at which the light has faded out.
+    pub radius: f32,
+    pub color: (f32, f32, f32),
+}
+
+impl Component for LightSource {
+    type Storage = DenseVecStorage<Self>;
+}
+
 #[derive(Debug, Clone, Copy)]
-struct Vec(Vector3<f32>);
+struct Vec3(Vector3<f32>);
 
-impl InterpolationPrimitive for Vec {
+impl InterpolationPrimitive for Vec3 {
     fn add(&self, other: &Self) -> Self {
         Self(Vector3::new(
             self.0.x + other.0.x,
@@ -122,3 +355,105 @@ impl InterpolationPrimitive for Vec {
         self.dot(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: u32, y: u32) -> Position {
+        Position(Point3::new(x, y, 0))
+    }
+
+    fn grid() -> MapGrid {
+        MapGrid::new(Vector3::new(10, 10, 1), Vector3::new(32, 32, 1))
+    }
+
+    /// Two steps east, a second each, starting at 10s.
+    fn two_steps(easing: Easing) -> MovingObject {
+        MovingObject::path(
+            10.0,
+            Duration::from_secs(1),
+            &grid(),
+            at(0, 0),
+            &[at(1, 0), at(2, 0)],
+        )
+        .with_easing(easing)
+    }
+
+    fn world(p: Position) -> Vector3<f32> {
+        grid().to_world(&p.0)
+    }
+
+    fn assert_near(a: Vector3<f32>, b: Vector3<f32>) {
+        assert!((a - b).norm() < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn segments_change_at_each_waypoint() {
+        let mob = two_steps(Easing::Linear);
+        assert_eq!(mob.segment_at(9.0), Some((at(0, 0), at(1, 0), 0.0)));
+        assert_eq!(mob.segment_at(10.0), Some((at(0, 0), at(1, 0), 0.0)));
+        assert_eq!(mob.segment_at(10.5), Some((at(0, 0), at(1, 0), 0.5)));
+        assert_eq!(mob.segment_at(11.0), Some((at(1, 0), at(2, 0), 0.0)));
+        assert_eq!(mob.segment_at(12.0), Some((at(1, 0), at(2, 0), 1.0)));
+        assert_eq!(mob.segment_at(12.5), None);
+    }
+
+    #[test]
+    fn position_is_the_last_waypoint_passed() {
+        let mob = two_steps(Easing::Linear);
+        assert_eq!(mob.position_at(9.0), at(0, 0));
+        assert_eq!(mob.position_at(10.9), at(0, 0));
+        assert_eq!(mob.position_at(11.0), at(1, 0));
+        assert_eq!(mob.position_at(12.0), at(2, 0));
+        assert_eq!(mob.position_at(20.0), at(2, 0));
+        assert_eq!(mob.end_p, at(2, 0));
+    }
+
+    #[test]
+    fn diagonal_steps_take_longer() {
+        let mob = MovingObject::new(0.0, Duration::from_secs(1), &grid(), at(0, 0), at(1, 1));
+        assert!((mob.end_time() - 2f64.sqrt()).abs() < 1e-6);
+        assert!(!mob.is_done(1.4));
+        assert!(mob.is_done(1.5));
+    }
+
+    #[test]
+    fn every_easing_starts_and_ends_on_its_tiles() {
+        for &easing in &[
+            Easing::Linear,
+            Easing::EaseInOut,
+            Easing::CubicSpline,
+            Easing::Step,
+        ] {
+            let mob = two_steps(easing);
+            assert_near(mob.interpolate(10.0), world(at(0, 0)));
+            assert_near(mob.interpolate(12.0), world(at(2, 0)));
+            assert_near(mob.interpolate(13.0), world(at(2, 0)));
+        }
+    }
+
+    #[test]
+    fn easings_shape_the_move() {
+        let x = |easing, now| two_steps(easing).interpolate(now).x;
+        let (start, end) = (world(at(0, 0)).x, world(at(2, 0)).x);
+
+        assert!((x(Easing::Linear, 10.5) - (start + 16.0)).abs() < 1e-3);
+        assert!((x(Easing::Linear, 11.5) - (start + 48.0)).abs() < 1e-3);
+
+        // Slower than linear off the start and into the end, level halfway
+        assert!(x(Easing::EaseInOut, 10.5) < x(Easing::Linear, 10.5));
+        assert!(x(Easing::EaseInOut, 11.5) > x(Easing::Linear, 11.5));
+        assert!((x(Easing::EaseInOut, 11.0) - x(Easing::Linear, 11.0)).abs() < 1e-3);
+
+        // Through the waypoint in between, and smoothly between them
+        assert!((x(Easing::CubicSpline, 11.0) - (start + 32.0)).abs() < 1e-3);
+        let spline = x(Easing::CubicSpline, 10.5);
+        assert!(spline > start && spline < start + 32.0);
+
+        // Stays on each waypoint until the next is reached
+        assert!((x(Easing::Step, 10.9) - start).abs() < 1e-3);
+        assert!((x(Easing::Step, 11.5) - (start + 32.0)).abs() < 1e-3);
+        assert!((x(Easing::Step, 12.0) - end).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,152 @@
+use amethyst::input::{InputEvent, StringBindings};
+
+/// Reduces a pair of axis values to a single step along whichever axis is
+/// pushed further, ignoring anything inside `deadzone`. Keyboard and d-pad
+/// input is always -1, 0 or 1 so only the analog stick is affected.
+pub fn dominant_axis(x: f32, y: f32, deadzone: f32) -> (f32, f32) {
+    let (ax, ay) = (x.abs(), y.abs());
+    if ax.max(ay) <= deadzone {
+        (0.0, 0.0)
+    } else if ax >= ay {
+        (x.signum(), 0.0)
+    } else {
+        (0.0, y.signum())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuCommand {
+    Previous,
+    Next,
+    Confirm,
+    Cancel,
+}
+
+/// Turns the `north_south` axis and the `confirm`/`cancel` actions into menu
+/// commands, repeating while the axis is held.
+#[derive(Clone, Debug)]
+pub struct MenuNavigator {
+    pub deadzone: f32,
+    /// Seconds to hold before the selection starts repeating.
+    pub repeat_delay: f32,
+    /// Seconds between repeats once it has started.
+    pub repeat_interval: f32,
+    held_direction: i32,
+    held_for: f32,
+    next_repeat: f32,
+}
+
+impl Default for MenuNavigator {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.5,
+            repeat_delay: 0.4,
+            repeat_interval: 0.12,
+            held_direction: 0,
+            held_for: 0.0,
+            next_repeat: 0.0,
+        }
+    }
+}
+
+impl MenuNavigator {
+    pub fn update<'a>(
+        &mut self,
+        axis: f32,
+        events: impl IntoIterator<Item = &'a InputEvent<StringBindings>>,
+        delta_seconds: f32,
+    ) -> Vec<MenuCommand> {
+        let mut commands = Vec::new();
+
+        let direction = if axis > self.deadzone {
+            1
+        } else if axis < -self.deadzone {
+            -1
+        } else {
+            0
+        };
+        if direction != self.held_direction {
+            self.held_direction = direction;
+            self.held_for = 0.0;
+            self.next_repeat = self.repeat_delay;
+            if direction != 0 {
+                commands.push(Self::step(direction));
+            }
+        } else if direction != 0 {
+            self.held_for += delta_seconds;
+            while self.held_for >= self.next_repeat {
+                self.next_repeat += self.repeat_interval;
+                commands.push(Self::step(direction));
+            }
+        }
+
+        for event in events {
+            if let InputEvent::ActionPressed(action) = event {
+                match action.as_str() {
+                    "confirm" => commands.push(MenuCommand::Confirm),
+                    "cancel" => commands.push(MenuCommand::Cancel),
+                    _ => {}
+                }
+            }
+        }
+        commands
+    }
+
+    fn step(direction: i32) -> MenuCommand {
+        // North is up the screen, so it moves to the previous item
+        if direction > 0 {
+            MenuCommand::Previous
+        } else {
+            MenuCommand::Next
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn none() -> Vec<InputEvent<StringBindings>> {
+        Vec::new()
+    }
+
+    #[test]
+    fn dominant_axis_picks_larger() {
+        assert_eq!(dominant_axis(0.9, 0.4, 0.25), (1.0, 0.0));
+        assert_eq!(dominant_axis(-0.3, -0.8, 0.25), (0.0, -1.0));
+        assert_eq!(dominant_axis(0.2, -0.1, 0.25), (0.0, 0.0));
+        assert_eq!(dominant_axis(-1.0, 0.0, 0.25), (-1.0, 0.0));
+    }
+
+    #[test]
+    fn axis_steps_once_then_repeats() {
+        let mut nav = MenuNavigator::default();
+        assert_eq!(nav.update(-1.0, &none(), 0.016), vec![MenuCommand::Next]);
+        assert!(nav.update(-1.0, &none(), 0.2).is_empty());
+        assert_eq!(nav.update(-1.0, &none(), 0.2), vec![MenuCommand::Next]);
+        assert_eq!(nav.update(-1.0, &none(), 0.12), vec![MenuCommand::Next]);
+        assert!(nav.update(0.0, &none(), 0.016).is_empty());
+        assert_eq!(nav.update(0.8, &none(), 0.016), vec![MenuCommand::Previous]);
+    }
+
+    #[test]
+    fn stick_inside_deadzone_is_ignored() {
+        let mut nav = MenuNavigator::default();
+        assert!(nav.update(0.3, &none(), 0.016).is_empty());
+        assert!(nav.update(-0.3, &none(), 1.0).is_empty());
+    }
+
+    #[test]
+    fn actions_confirm_and_cancel() {
+        let mut nav = MenuNavigator::default();
+        let events = vec![
+            InputEvent::ActionPressed("confirm".to_string()),
+            InputEvent::ActionPressed("interact".to_string()),
+            InputEvent::ActionPressed("cancel".to_string()),
+        ];
+        assert_eq!(
+            nav.update(0.0, &events, 0.016),
+            vec![MenuCommand::Confirm, MenuCommand::Cancel]
+        );
+    }
+}
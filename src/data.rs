@@ -0,0 +1,319 @@
+use amethyst::{
+    assets::{Asset, Handle, ProcessingState},
+    ecs::VecStorage,
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{autotile::Autotile, clock::TimeOfDay, level::LevelTile, rng::Rng};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct MonsterData {
+    pub name: String,
+    pub hp: u32,
+    pub attack: u32,
+    pub defense: u32,
+    pub experience: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct MonsterList {
+    pub monsters: Vec<MonsterData>,
+}
+
+impl MonsterList {
+    /// Every monster in `lists`, as one list.
+    pub fn merged<'a>(lists: impl IntoIterator<Item = &'a MonsterList>) -> Self {
+        let monsters = lists
+            .into_iter()
+            .flat_map(|list| list.monsters.iter().cloned())
+            .collect();
+        Self { monsters }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&MonsterData> {
+        self.monsters.iter().find(|monster| monster.name == name)
+    }
+}
+
+impl Asset for MonsterList {
+    const NAME: &'static str = "dd::MonsterList";
+    type Data = Self;
+    type HandleStorage = VecStorage<Handle<Self>>;
+}
+
+impl From<MonsterList> for Result<ProcessingState<MonsterList>> {
+    fn from(list: MonsterList) -> Result<ProcessingState<MonsterList>> {
+        Ok(ProcessingState::Loaded(list))
+    }
+}
+
+/// The monsters that may attack as the player walks a level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EncounterTable {
+    /// Chance of an encounter on each step.
+    pub rate: f32,
+    pub monsters: Vec<Encounter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Encounter {
+    /// Name of a monster in the monster lists.
+    pub monster: String,
+    /// How likely the monster is relative to the others about.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// When the monster is about, at any time if empty.
+    #[serde(default)]
+    pub times: Vec<TimeOfDay>,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl EncounterTable {
+    /// Picks one of the monsters about at `time`, by weight.
+    pub fn pick(&self, time: TimeOfDay, rng: &mut Rng) -> Option<&str> {
+        let about: Vec<_> = self
+            .monsters
+            .iter()
+            .filter(|encounter| encounter.times.is_empty() || encounter.times.contains(&time))
+            .collect();
+        let mut roll = rng.below(about.iter().map(|encounter| encounter.weight).sum());
+        for encounter in about {
+            if roll < encounter.weight {
+                return Some(&encounter.monster);
+            }
+            roll -= encounter.weight;
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct ItemData {
+    pub name: String,
+    pub description: String,
+    pub value: u32,
+    /// How far the party sees in the dark while carrying the item.
+    #[serde(default)]
+    pub light_radius: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct ItemList {
+    pub items: Vec<ItemData>,
+}
+
+impl ItemList {
+    /// Every item in `lists`, as one list.
+    pub fn merged<'a>(lists: impl IntoIterator<Item = &'a ItemList>) -> Self {
+        let items = lists
+            .into_iter()
+            .flat_map(|list| list.items.iter().cloned())
+            .collect();
+        Self { items }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ItemData> {
+        self.items.iter().find(|item| item.name == name)
+    }
+}
+
+impl Asset for ItemList {
+    const NAME: &'static str = "dd::ItemList";
+    type Data = Self;
+    type HandleStorage = VecStorage<Handle<Self>>;
+}
+
+impl From<ItemList> for Result<ProcessingState<ItemList>> {
+    fn from(list: ItemList) -> Result<ProcessingState<ItemList>> {
+        Ok(ProcessingState::Loaded(list))
+    }
+}
+
+/// Per-tile gameplay properties.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileProperties {
+    /// Multiplier on the time it takes to step onto the tile.
+    pub step_cost: f32,
+    /// Damage dealt to each party member on stepping onto the tile.
+    pub damage: u32,
+    /// How the tile is drawn.
+    pub sprite: Option<TileSprite>,
+    /// Picks the sprite from the neighbouring tiles instead.
+    pub autotile: Option<Autotile>,
+    /// The tileset the sprite numbers are in, rather than the level's own.
+    pub sheet: Option<String>,
+}
+
+impl Default for TileProperties {
+    fn default() -> Self {
+        Self {
+            step_cost: 1.0,
+            damage: 0,
+            sprite: None,
+            autotile: None,
+            sheet: None,
+        }
+    }
+}
+
+/// Sprites from the terrain sheet shown in turn, `frame_secs` each, all
+/// tiles of a kind in step. A single frame doesn't animate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TileSprite {
+    pub frames: Vec<usize>,
+    #[serde(default)]
+    pub frame_secs: f32,
+}
+
+impl TileSprite {
+    /// The frame to show `seconds` into the game.
+    pub fn frame_at(&self, seconds: f64) -> Option<usize> {
+        if self.frames.len() > 1 && self.frame_secs > 0.0 {
+            let n = (seconds / f64::from(self.frame_secs)) as usize;
+            Some(self.frames[n % self.frames.len()])
+        } else {
+            self.frames.first().cloned()
+        }
+    }
+}
+
+/// Movement timing and terrain properties, read from `data/terrain.yaml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Terrain {
+    /// Seconds to walk one plain tile.
+    pub base_step_secs: f32,
+    /// Multiplier on step time while the run button is held.
+    pub run_multiplier: f32,
+    pub tiles: HashMap<LevelTile, TileProperties>,
+}
+
+impl Default for Terrain {
+    fn default() -> Self {
+        Self {
+            base_step_secs: 0.2,
+            run_multiplier: 0.5,
+            tiles: HashMap::new(),
+        }
+    }
+}
+
+impl Terrain {
+    /// The sprite for `tile` at `seconds`, if the terrain gives it one.
+    pub fn sprite_at(&self, tile: LevelTile, seconds: f64) -> Option<usize> {
+        self.tiles
+            .get(&tile)
+            .and_then(|properties| properties.sprite.as_ref())
+            .and_then(|sprite| sprite.frame_at(seconds))
+    }
+
+    /// How `tile` joins up with its neighbours, if it does.
+    pub fn autotile(&self, tile: LevelTile) -> Option<&Autotile> {
+        self.tiles
+            .get(&tile)
+            .and_then(|properties| properties.autotile.as_ref())
+    }
+
+    pub fn properties(&self, tile: LevelTile) -> TileProperties {
+        self.tiles.get(&tile).cloned().unwrap_or_default()
+    }
+
+    /// Seconds to step onto `tile`, before the user's movement speed setting.
+    pub fn step_secs(&self, tile: LevelTile, running: bool) -> f32 {
+        let run = if running { self.run_multiplier } else { 1.0 };
+        self.base_step_secs * self.properties(tile).step_cost * run
+    }
+}
+
+impl Asset for Terrain {
+    const NAME: &'static str = "dd::Terrain";
+    type Data = Self;
+    type HandleStorage = VecStorage<Handle<Self>>;
+}
+
+impl From<Terrain> for Result<ProcessingState<Terrain>> {
+    fn from(terrain: Terrain) -> Result<ProcessingState<Terrain>> {
+        Ok(ProcessingState::Loaded(terrain))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_frames_cycle() {
+        let sprite = TileSprite {
+            frames: vec![2, 3, 4],
+            frame_secs: 0.5,
+        };
+        let frames: Vec<_> = [0.0, 0.4, 0.5, 1.2, 1.5, 10.1]
+            .iter()
+            .map(|&t| sprite.frame_at(t))
+            .collect();
+        assert_eq!(
+            frames,
+            vec![Some(2), Some(2), Some(3), Some(4), Some(2), Some(4)]
+        );
+
+        let still = TileSprite {
+            frames: vec![7],
+            frame_secs: 0.0,
+        };
+        assert_eq!(still.frame_at(3.0), Some(7));
+        let none = TileSprite {
+            frames: vec![],
+            frame_secs: 1.0,
+        };
+        assert_eq!(none.frame_at(3.0), None);
+    }
+
+    #[test]
+    fn encounters_depend_on_time_of_day() {
+        let table: EncounterTable = serde_yaml::from_str(
+            "
+            rate: 0.1
+            monsters:
+              - monster: Slime
+                weight: 3
+              - monster: Bat
+                times: [Dusk, Night]
+            ",
+        )
+        .unwrap();
+        let mut rng = Rng::new(3);
+        let picks: Vec<_> = (0..100)
+            .map(|_| table.pick(TimeOfDay::Day, &mut rng))
+            .collect();
+        assert!(picks.iter().all(|&pick| pick == Some("Slime")));
+        let picks: Vec<_> = (0..100)
+            .map(|_| table.pick(TimeOfDay::Night, &mut rng))
+            .collect();
+        assert!(picks.contains(&Some("Bat")));
+        assert!(picks.contains(&Some("Slime")));
+
+        let empty = EncounterTable {
+            rate: 1.0,
+            monsters: vec![],
+        };
+        assert_eq!(empty.pick(TimeOfDay::Day, &mut rng), None);
+    }
+
+    #[test]
+    fn terrain_data_parses() {
+        let path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/data/terrain.yaml");
+        let terrain: Terrain = serde_yaml::from_reader(std::fs::File::open(path).unwrap()).unwrap();
+        assert_eq!(terrain.sprite_at(LevelTile::Grass, 100.0), Some(1));
+        assert_eq!(terrain.sprite_at(LevelTile::Water, 0.0), Some(2));
+        assert_eq!(terrain.sprite_at(LevelTile::Water, 0.5), Some(3));
+        assert_eq!(terrain.sprite_at(LevelTile::Fence, 0.0), None);
+        assert_eq!(terrain.properties(LevelTile::Lava).damage, 3);
+    }
+}
@@ -0,0 +1,67 @@
+use amethyst::core::math::Point2;
+
+use super::{rooms::random_room, Carving, DungeonConfig, Rect};
+use crate::rng::Rng;
+
+/// Splits the floor in two, then each half in two, and so on until the
+/// parts are too small to split or there are `config.rooms` of them. Each
+/// part gets a room, and the two halves of every split are joined by a
+/// corridor.
+pub(super) fn carve(carving: &mut Carving, config: &DungeonConfig, rng: &mut Rng) {
+    let inside = carving.interior();
+    split(carving, inside, config.rooms.max(1), config, rng);
+}
+
+/// Digs out `part` as up to `rooms` rooms, returning a tile in one of them.
+fn split(
+    carving: &mut Carving,
+    part: Rect,
+    rooms: u32,
+    config: &DungeonConfig,
+    rng: &mut Rng,
+) -> Point2<u32> {
+    // Each half must still fit the smallest room with a wall between
+    let min = config.room_size.0.max(1) + 1;
+    let across = part.w >= part.h;
+    let length = if across { part.w } else { part.h };
+    if rooms < 2 || length < min * 2 {
+        // Kept off the far edges, clear of the parts next to this one
+        let within = Rect {
+            w: (part.w - 1).max(1),
+            h: (part.h - 1).max(1),
+            ..part
+        };
+        let room = random_room(within, config.room_size, rng);
+        carving.carve_rect(room);
+        return room.centre();
+    }
+
+    let cut = min + rng.below(length - min * 2 + 1);
+    let (first, second) = if across {
+        (
+            Rect { w: cut, ..part },
+            Rect {
+                x: part.x + cut,
+                w: part.w - cut,
+                ..part
+            },
+        )
+    } else {
+        (
+            Rect { h: cut, ..part },
+            Rect {
+                y: part.y + cut,
+                h: part.h - cut,
+                ..part
+            },
+        )
+    };
+    let a = split(carving, first, rooms / 2, config, rng);
+    let b = split(carving, second, rooms - rooms / 2, config, rng);
+    carving.carve_corridor(a, b, rng);
+    if rng.chance(0.5) {
+        a
+    } else {
+        b
+    }
+}
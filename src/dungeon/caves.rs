@@ -0,0 +1,40 @@
+use amethyst::core::math::Point2;
+
+use super::{Carving, DungeonConfig};
+use crate::rng::Rng;
+
+/// Starts from noise, then over `config.cave_steps` rounds turns each tile
+/// to rock if most of the tiles around it are, and opens it up if most are
+/// open.
+pub(super) fn carve(carving: &mut Carving, config: &DungeonConfig, rng: &mut Rng) {
+    let inside = carving.interior();
+    let tiles: Vec<Point2<u32>> = (inside.y..inside.y + inside.h)
+        .flat_map(|y| (inside.x..inside.x + inside.w).map(move |x| Point2::new(x, y)))
+        .collect();
+    for &p in &tiles {
+        carving.set(p, !rng.chance(config.cave_fill));
+    }
+    for _ in 0..config.cave_steps {
+        let last = carving.clone();
+        for &p in &tiles {
+            match rock_around(&last, p) {
+                0..=3 => carving.set(p, true),
+                4 => {}
+                _ => carving.set(p, false),
+            }
+        }
+    }
+}
+
+/// How many of the eight tiles around `p` are rock.
+fn rock_around(carving: &Carving, p: Point2<u32>) -> usize {
+    let mut rock = 0;
+    for x in p.x - 1..=p.x + 1 {
+        for y in p.y - 1..=p.y + 1 {
+            if (x, y) != (p.x, p.y) && !carving.is_open(Point2::new(x, y)) {
+                rock += 1;
+            }
+        }
+    }
+    rock
+}
@@ -0,0 +1,598 @@
+use amethyst::{core::math::Point2, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt, fs,
+    path::PathBuf,
+    slice::Iter,
+    str::FromStr,
+};
+
+use crate::{
+    data::EncounterTable,
+    level::{Level, LevelTile, Marker, MarkerKind},
+    rng::Rng,
+};
+
+mod bsp;
+mod caves;
+mod rooms;
+
+/// Dungeon floors are numbered from here, one more for each floor down, so
+/// they don't clash with the levels in the manifest.
+pub const DUNGEON_LEVELS: i32 = 1000;
+
+/// How the rock is carved out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Algorithm {
+    /// Rooms scattered at random, each joined to the last by a corridor.
+    Rooms,
+    /// Caves grown by smoothing random noise.
+    Caves,
+    /// The map split in two over and over, with a room in each part.
+    Bsp,
+}
+
+impl FromStr for Algorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "rooms" => Ok(Self::Rooms),
+            "caves" => Ok(Self::Caves),
+            "bsp" => Ok(Self::Bsp),
+            _ => Err(Error::new(DungeonError::UnknownAlgorithm(s.to_string()))),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DungeonError {
+    UnknownAlgorithm(String),
+    /// A command line flag without a value, or with one that didn't parse.
+    MissingValue(String),
+}
+
+impl std::error::Error for DungeonError {}
+
+impl fmt::Display for DungeonError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use self::DungeonError::*;
+
+        match self {
+            UnknownAlgorithm(name) => write!(fmt, "Unknown dungeon algorithm `{}`", name),
+            MissingValue(flag) => write!(fmt, "`{}` needs a value", flag),
+        }
+    }
+}
+
+/// How random dungeons are generated, read from `resources/dungeon.ron`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DungeonConfig {
+    pub algorithm: Algorithm,
+    pub width: usize,
+    pub height: usize,
+    /// Rooms tried for `Rooms`, and the most parts `Bsp` splits into.
+    pub rooms: u32,
+    /// Smallest and largest sides of a room, in tiles.
+    pub room_size: (u32, u32),
+    /// Chance each tile of a cave starts out as rock.
+    pub cave_fill: f32,
+    /// Rounds of smoothing the caves get.
+    pub cave_steps: u32,
+    pub chests: u32,
+    /// Names of the items a chest may hold, from the item lists.
+    pub chest_items: Vec<String>,
+    pub encounter_zones: u32,
+    pub zone_radius: u32,
+    pub encounters: Option<EncounterTable>,
+    pub sight: Option<u32>,
+    pub tilesets: Vec<String>,
+}
+
+impl Default for DungeonConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::Rooms,
+            width: 48,
+            height: 32,
+            rooms: 12,
+            room_size: (4, 9),
+            cave_fill: 0.45,
+            cave_steps: 4,
+            chests: 3,
+            chest_items: Vec::new(),
+            encounter_zones: 3,
+            zone_radius: 4,
+            encounters: None,
+            sight: Some(6),
+            tilesets: Vec::new(),
+        }
+    }
+}
+
+/// One floor of a random dungeon. The same floor always comes out the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DungeonFloor {
+    /// Shared by every floor of the dungeon.
+    pub seed: u64,
+    /// 1 for the first floor down.
+    pub depth: u32,
+}
+
+impl DungeonFloor {
+    /// The first floor of the dungeon grown from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self { seed, depth: 1 }
+    }
+
+    /// The next floor down.
+    pub fn below(self) -> Self {
+        Self {
+            depth: self.depth + 1,
+            ..self
+        }
+    }
+
+    /// The floor's level number.
+    pub fn level(self) -> i32 {
+        DUNGEON_LEVELS + self.depth as i32
+    }
+
+    fn rng(self) -> Rng {
+        Rng::new(
+            self.seed
+                .wrapping_add(u64::from(self.depth).wrapping_mul(0x2545_F491_4F6C_DD1D)),
+        )
+    }
+}
+
+/// The dungeon floor the player is on, `None` on the manifest's levels.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CurrentFloor(pub Option<DungeonFloor>);
+
+/// The chests the party has opened, by level and position, so they aren't
+/// filled again when the level is next spawned.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpenedChests(pub HashMap<i32, HashSet<(u32, u32)>>);
+
+impl OpenedChests {
+    pub fn open(&mut self, level: i32, position: (u32, u32)) {
+        self.0.entry(level).or_default().insert(position);
+    }
+
+    pub fn is_opened(&self, level: i32, position: (u32, u32)) -> bool {
+        self.0
+            .get(&level)
+            .map_or(false, |opened| opened.contains(&position))
+    }
+}
+
+/// Builds the level for `floor`, with stairs up where the party arrives and
+/// stairs down as far from them as the floor allows.
+pub fn generate(config: &DungeonConfig, floor: DungeonFloor) -> Level {
+    let mut rng = floor.rng();
+    // Too small and there's no room inside the outer wall for anything
+    let mut carving = Carving::new(config.width.max(8), config.height.max(8));
+    match config.algorithm {
+        Algorithm::Rooms => rooms::carve(&mut carving, config, &mut rng),
+        Algorithm::Caves => caves::carve(&mut carving, config, &mut rng),
+        Algorithm::Bsp => bsp::carve(&mut carving, config, &mut rng),
+    }
+    carving.connect();
+
+    let mut level = Level::filled(
+        floor.level(),
+        &format!("Dungeon floor {}", floor.depth),
+        carving.width,
+        carving.height,
+        LevelTile::Wall,
+    );
+    for p in carving.open_tiles() {
+        level.set_tile(p, LevelTile::Plain);
+    }
+    level.sight = config.sight;
+    level.tilesets = config.tilesets.clone();
+    level.encounters = config.encounters.clone();
+    level.markers = place_markers(&carving, config, &mut rng);
+    level
+}
+
+/// Where the party arrives on a generated floor.
+pub fn arrival(level: &Level) -> Option<(u32, u32)> {
+    level
+        .markers
+        .iter()
+        .find(|marker| marker.kind == MarkerKind::Stairs { down: false })
+        .map(|marker| marker.position)
+}
+
+/// Stairs up on a random tile, stairs down on the tile furthest from them,
+/// then chests and encounter zones on tiles nothing else is on.
+fn place_markers(carving: &Carving, config: &DungeonConfig, rng: &mut Rng) -> Vec<Marker> {
+    let open = carving.open_tiles();
+    let start = open[rng.below(open.len() as u32) as usize];
+    let distances = carving.distances(start);
+    let end = open
+        .iter()
+        .cloned()
+        .max_by_key(|p| distances[carving.index(*p)])
+        .unwrap_or(start);
+
+    let marker = |p: Point2<u32>, kind| Marker {
+        position: (p.x, p.y),
+        kind,
+    };
+    let mut markers = vec![
+        marker(start, MarkerKind::Stairs { down: false }),
+        marker(end, MarkerKind::Stairs { down: true }),
+    ];
+    let mut taken: HashSet<Point2<u32>> = [start, end].iter().cloned().collect();
+    let mut free_tile = |rng: &mut Rng| {
+        let free: Vec<_> = open.iter().filter(|p| !taken.contains(p)).collect();
+        let p = **free.get(rng.below(free.len() as u32) as usize)?;
+        taken.insert(p);
+        Some(p)
+    };
+    for _ in 0..config.chests {
+        let item = match config.chest_items.len() {
+            0 => None,
+            n => Some(config.chest_items[rng.below(n as u32) as usize].clone()),
+        };
+        if let Some(p) = free_tile(rng) {
+            markers.push(marker(p, MarkerKind::Chest { item }));
+        }
+    }
+    for _ in 0..config.encounter_zones {
+        if let Some(p) = free_tile(rng) {
+            let radius = config.zone_radius;
+            markers.push(marker(p, MarkerKind::EncounterZone { radius }));
+        }
+    }
+    markers
+}
+
+/// The open and solid tiles of a floor while it's being dug out. The
+/// outermost tiles are never opened, so every floor has a wall round it.
+#[derive(Debug, Clone)]
+struct Carving {
+    width: usize,
+    height: usize,
+    open: Vec<bool>,
+}
+
+/// Part of a floor, from `(x, y)` up to but not including `(x + w, y + h)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+impl Rect {
+    fn centre(&self) -> Point2<u32> {
+        Point2::new(self.x + self.w / 2, self.y + self.h / 2)
+    }
+
+    /// Whether the rects overlap or touch.
+    fn touches(&self, other: &Rect) -> bool {
+        self.x <= other.x + other.w
+            && other.x <= self.x + self.w
+            && self.y <= other.y + other.h
+            && other.y <= self.y + self.h
+    }
+}
+
+impl Carving {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            open: vec![false; width * height],
+        }
+    }
+
+    /// Everywhere inside the outer wall.
+    fn interior(&self) -> Rect {
+        Rect {
+            x: 1,
+            y: 1,
+            w: self.width as u32 - 2,
+            h: self.height as u32 - 2,
+        }
+    }
+
+    fn index(&self, p: Point2<u32>) -> usize {
+        p.y as usize * self.width + p.x as usize
+    }
+
+    fn is_inside(&self, p: Point2<u32>) -> bool {
+        p.x > 0 && p.y > 0 && (p.x as usize) < self.width - 1 && (p.y as usize) < self.height - 1
+    }
+
+    fn is_open(&self, p: Point2<u32>) -> bool {
+        self.open[self.index(p)]
+    }
+
+    fn set(&mut self, p: Point2<u32>, open: bool) {
+        if self.is_inside(p) {
+            let i = self.index(p);
+            self.open[i] = open;
+        }
+    }
+
+    fn carve_rect(&mut self, rect: Rect) {
+        for x in rect.x..rect.x + rect.w {
+            for y in rect.y..rect.y + rect.h {
+                self.set(Point2::new(x, y), true);
+            }
+        }
+    }
+
+    /// An L-shaped corridor between `from` and `to`, turning at one of the
+    /// two corners.
+    fn carve_corridor(&mut self, from: Point2<u32>, to: Point2<u32>, rng: &mut Rng) {
+        let corner = if rng.chance(0.5) {
+            Point2::new(to.x, from.y)
+        } else {
+            Point2::new(from.x, to.y)
+        };
+        for &(a, b) in &[(from, corner), (corner, to)] {
+            for x in a.x.min(b.x)..=a.x.max(b.x) {
+                for y in a.y.min(b.y)..=a.y.max(b.y) {
+                    self.set(Point2::new(x, y), true);
+                }
+            }
+        }
+    }
+
+    fn open_tiles(&self) -> Vec<Point2<u32>> {
+        (0..self.height as u32)
+            .flat_map(|y| (0..self.width as u32).map(move |x| Point2::new(x, y)))
+            .filter(|p| self.is_open(*p))
+            .collect()
+    }
+
+    /// The four tiles beside `p` that aren't the outer wall.
+    fn neighbours(&self, p: Point2<u32>) -> impl Iterator<Item = Point2<u32>> + '_ {
+        let (x, y) = (i64::from(p.x), i64::from(p.y));
+        [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+            .to_vec()
+            .into_iter()
+            .filter(|&(x, y)| x >= 0 && y >= 0)
+            .map(|(x, y)| Point2::new(x as u32, y as u32))
+            .filter(move |p| self.is_inside(*p))
+    }
+
+    /// Steps from `start` to each tile over open ground, `usize::MAX` for
+    /// those it can't reach.
+    fn distances(&self, start: Point2<u32>) -> Vec<usize> {
+        let mut distances = vec![usize::max_value(); self.open.len()];
+        distances[self.index(start)] = 0;
+        let mut queue = VecDeque::from(vec![start]);
+        while let Some(p) = queue.pop_front() {
+            let next = distances[self.index(p)] + 1;
+            for q in self.neighbours(p) {
+                let i = self.index(q);
+                if self.open[i] && distances[i] == usize::max_value() {
+                    distances[i] = next;
+                    queue.push_back(q);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Tunnels from each open area to the nearest other one through the
+    /// rock, until every open tile can be walked to from every other.
+    fn connect(&mut self) {
+        if !self.open.contains(&true) {
+            // Nothing was dug at all, so make a start
+            let centre = self.interior().centre();
+            self.set(centre, true);
+        }
+        loop {
+            let first = self.open_tiles()[0];
+            let distances = self.distances(first);
+            let unreached = self
+                .open_tiles()
+                .into_iter()
+                .find(|p| distances[self.index(*p)] == usize::max_value());
+            match unreached {
+                Some(p) => self.tunnel(p),
+                None => break,
+            }
+        }
+    }
+
+    /// Digs the shortest way from the open area around `from` to any open
+    /// tile outside it.
+    fn tunnel(&mut self, from: Point2<u32>) {
+        let area = self.distances(from);
+        let mut came_from: Vec<Option<Point2<u32>>> = vec![None; self.open.len()];
+        let mut seen: Vec<bool> = area.iter().map(|&d| d != usize::max_value()).collect();
+        let mut queue: VecDeque<Point2<u32>> = self
+            .open_tiles()
+            .into_iter()
+            .filter(|p| seen[self.index(*p)])
+            .collect();
+        while let Some(p) = queue.pop_front() {
+            let neighbours: Vec<_> = self.neighbours(p).collect();
+            for q in neighbours {
+                let i = self.index(q);
+                if seen[i] {
+                    continue;
+                }
+                seen[i] = true;
+                came_from[i] = Some(p);
+                if self.open[i] {
+                    // Back through the rock to where the tunnel started
+                    let mut at = p;
+                    while !self.is_open(at) {
+                        self.set(at, true);
+                        at = came_from[self.index(at)].expect("Tunnel leads back to the area");
+                    }
+                    return;
+                }
+                queue.push_back(q);
+            }
+        }
+    }
+}
+
+/// Writes a dungeon floor out in the level YAML format, from the command
+/// line arguments following `dd generate`:
+///
+/// `[rooms|caves|bsp] [--seed N] [--depth N] [--width N] [--height N] [--out FILE]`
+///
+/// Anything not given comes from `config`, with a random seed. The level
+/// goes to standard output without `--out`.
+pub fn export(args: &[String], mut config: DungeonConfig) -> Result<()> {
+    let mut floor = DungeonFloor::new(Rng::default().next_u64());
+    let mut out: Option<PathBuf> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => floor.seed = value(&mut args, arg)?,
+            "--depth" => floor.depth = value(&mut args, arg)?,
+            "--width" => config.width = value(&mut args, arg)?,
+            "--height" => config.height = value(&mut args, arg)?,
+            "--out" => out = Some(value(&mut args, arg)?),
+            algorithm => config.algorithm = algorithm.parse()?,
+        }
+    }
+
+    let yaml = generate(&config, floor).to_yaml()?;
+    match out {
+        Some(path) => {
+            fs::write(&path, yaml)?;
+            eprintln!("Wrote seed {} to {}", floor.seed, path.display());
+        }
+        None => print!("{}", yaml),
+    }
+    Ok(())
+}
+
+/// The value following `flag` on the command line.
+fn value<T: FromStr>(args: &mut Iter<'_, String>, flag: &str) -> Result<T> {
+    args.next()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| Error::new(DungeonError::MissingValue(flag.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::core::math::Point3;
+
+    use crate::grid::Direction;
+
+    fn config(algorithm: Algorithm) -> DungeonConfig {
+        DungeonConfig {
+            algorithm,
+            chest_items: vec!["Potion".to_string()],
+            ..DungeonConfig::default()
+        }
+    }
+
+    const ALGORITHMS: [Algorithm; 3] = [Algorithm::Rooms, Algorithm::Caves, Algorithm::Bsp];
+
+    #[test]
+    fn floors_repeat_from_their_seed() {
+        for &algorithm in &ALGORITHMS {
+            let config = config(algorithm);
+            let floor = DungeonFloor::new(42);
+            assert_eq!(generate(&config, floor), generate(&config, floor));
+            assert_ne!(generate(&config, floor), generate(&config, floor.below()));
+            assert_ne!(
+                generate(&config, floor),
+                generate(&config, DungeonFloor::new(43))
+            );
+        }
+    }
+
+    #[test]
+    fn every_open_tile_is_reachable() {
+        for &algorithm in &ALGORITHMS {
+            for seed in 0..20 {
+                let level = generate(&config(algorithm), DungeonFloor::new(seed));
+                let (x, y) = arrival(&level).expect("Floor has stairs up");
+
+                // Everywhere the player can walk to from the stairs
+                let mut reached = HashSet::new();
+                let mut queue = VecDeque::from(vec![Point3::new(x, y, 0)]);
+                while let Some(p) = queue.pop_front() {
+                    if reached.insert(p) {
+                        queue.extend(Direction::CARDINAL.iter().filter_map(|&d| level.step(p, d)));
+                    }
+                }
+                let open = (0..level.width as u32)
+                    .flat_map(|x| (0..level.height as u32).map(move |y| Point2::new(x, y)))
+                    .filter(|p| !level.is_blocking(*p))
+                    .count();
+                assert_eq!(reached.len(), open, "{:?} seed {}", algorithm, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn markers_are_on_open_tiles() {
+        for &algorithm in &ALGORITHMS {
+            let level = generate(&config(algorithm), DungeonFloor::new(7));
+            assert_eq!(level.level, DUNGEON_LEVELS + 1);
+            let count =
+                |f: fn(&MarkerKind) -> bool| level.markers.iter().filter(|m| f(&m.kind)).count();
+            assert_eq!(count(|k| *k == MarkerKind::Stairs { down: true }), 1);
+            assert_eq!(count(|k| *k == MarkerKind::Stairs { down: false }), 1);
+            let potion = |k: &MarkerKind| match k {
+                MarkerKind::Chest { item } => item.as_ref().map(String::as_str) == Some("Potion"),
+                _ => false,
+            };
+            assert_eq!(count(potion), 3);
+            assert_eq!(count(|k| *k == MarkerKind::EncounterZone { radius: 4 }), 3);
+
+            let positions: HashSet<_> = level.markers.iter().map(|m| m.position).collect();
+            assert_eq!(positions.len(), level.markers.len());
+            for (x, y) in positions {
+                assert!(!level.is_blocking(Point2::new(x, y)));
+            }
+        }
+    }
+
+    #[test]
+    fn tunnels_join_separate_areas() {
+        let mut carving = Carving::new(10, 8);
+        carving.carve_rect(Rect {
+            x: 1,
+            y: 1,
+            w: 2,
+            h: 2,
+        });
+        carving.carve_rect(Rect {
+            x: 6,
+            y: 4,
+            w: 3,
+            h: 3,
+        });
+        carving.connect();
+        let distances = carving.distances(Point2::new(1, 1));
+        assert!(carving
+            .open_tiles()
+            .iter()
+            .all(|p| distances[carving.index(*p)] != usize::max_value()));
+        // Through no more rock than the gap between them
+        assert_eq!(carving.open_tiles().len(), 4 + 9 + 5);
+
+        // The outer wall stays whole
+        let mut carving = Carving::new(8, 8);
+        carving.carve_rect(Rect {
+            x: 0,
+            y: 0,
+            w: 8,
+            h: 8,
+        });
+        assert_eq!(carving.open_tiles().len(), 36);
+    }
+}
@@ -0,0 +1,35 @@
+use super::{Carving, DungeonConfig, Rect};
+use crate::rng::Rng;
+
+/// Tries `config.rooms` rooms at random spots, skipping any that would run
+/// into one already dug, and joins each room to the one before it.
+pub(super) fn carve(carving: &mut Carving, config: &DungeonConfig, rng: &mut Rng) {
+    let inside = carving.interior();
+    let mut rooms: Vec<Rect> = Vec::new();
+    for _ in 0..config.rooms {
+        let room = random_room(inside, config.room_size, rng);
+        if rooms.iter().any(|other| room.touches(other)) {
+            continue;
+        }
+        carving.carve_rect(room);
+        if let Some(last) = rooms.last() {
+            carving.carve_corridor(last.centre(), room.centre(), rng);
+        }
+        rooms.push(room);
+    }
+}
+
+/// A room with sides between the `size` limits somewhere within `bounds`,
+/// shrunk to fit if `bounds` is smaller than that.
+pub(super) fn random_room(bounds: Rect, size: (u32, u32), rng: &mut Rng) -> Rect {
+    let (min, max) = (size.0.max(1), size.1.max(size.0).max(1));
+    let side = |rng: &mut Rng, room: u32| (min + rng.below(max - min + 1)).min(room);
+    let w = side(rng, bounds.w);
+    let h = side(rng, bounds.h);
+    Rect {
+        x: bounds.x + rng.below(bounds.w - w + 1),
+        y: bounds.y + rng.below(bounds.h - h + 1),
+        w,
+        h,
+    }
+}
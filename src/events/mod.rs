@@ -10,7 +10,17 @@ use winit::Event;
 
 #[derive(Clone, Debug)]
 pub enum GameEvent {
-    Battle,
+    /// A monster, named in the monster lists, has attacked.
+    Battle(String),
+    /// The whole party is down.
+    GameOver,
+    /// The victory condition has been met.
+    Victory,
+    /// The player has taken the stairs down to the next dungeon floor.
+    Descend,
+    /// The level's file changed and the new level was swapped in, with
+    /// nothing spawned yet from its markers.
+    LevelReloaded,
 }
 
 #[derive(EventReader, Derivative, Debug)]
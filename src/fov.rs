@@ -0,0 +1,307 @@
+use amethyst::core::math::Point2;
+use std::collections::HashMap;
+
+use crate::{chunk::TileSet, level::Level};
+
+/// Which tiles of `level` can be seen from `origin` within `radius` tiles.
+/// Uses recursive shadowcasting, with opaque tiles themselves visible but
+/// hiding what is behind them.
+pub fn field_of_view(level: &Level, origin: Point2<u32>, radius: u32) -> TileSet {
+    let mut visible = TileSet::default();
+    let origin = match level.wrap(i64::from(origin.x), i64::from(origin.y)) {
+        Some(origin) => origin,
+        None => return visible,
+    };
+    visible.insert(origin);
+    let caster = Caster {
+        level,
+        origin: (i64::from(origin.x), i64::from(origin.y)),
+        radius: i64::from(radius),
+    };
+    for octant in OCTANTS.iter() {
+        caster.cast(&mut visible, 1, 1.0, 0.0, octant);
+    }
+    visible
+}
+
+/// Transforms from octant coordinates to map offsets.
+const OCTANTS: [[i64; 4]; 8] = [
+    [1, 0, 0, 1],
+    [0, 1, 1, 0],
+    [0, -1, 1, 0],
+    [-1, 0, 0, 1],
+    [-1, 0, 0, -1],
+    [0, -1, -1, 0],
+    [0, 1, -1, 0],
+    [1, 0, 0, -1],
+];
+
+struct Caster<'a> {
+    level: &'a Level,
+    origin: (i64, i64),
+    radius: i64,
+}
+
+impl Caster<'_> {
+    /// The tile at `(x, y)`, across edges that wrap.
+    fn tile(&self, x: i64, y: i64) -> Option<Point2<u32>> {
+        self.level.wrap(x, y)
+    }
+
+    fn is_opaque(&self, x: i64, y: i64) -> bool {
+        self.tile(x, y)
+            .and_then(|p| self.level.get_tile(p).ok())
+            .map(|tile| tile.is_opaque())
+            .unwrap_or(true)
+    }
+
+    /// Lights one row of an octant at a time between the `start` and `end`
+    /// slopes, recursing past each run of opaque tiles.
+    fn cast(&self, visible: &mut TileSet, row: i64, start: f64, end: f64, octant: &[i64; 4]) {
+        if start < end {
+            return;
+        }
+        let [xx, xy, yx, yy] = *octant;
+        let mut start = start;
+        let mut next_start = start;
+        for distance in row..=self.radius {
+            let dy = -distance;
+            let mut blocked = false;
+            for dx in -distance..=0 {
+                let x = self.origin.0 + dx * xx + dy * xy;
+                let y = self.origin.1 + dx * yx + dy * yy;
+                let left = (dx as f64 - 0.5) / (dy as f64 + 0.5);
+                let right = (dx as f64 + 0.5) / (dy as f64 - 0.5);
+                if start < right {
+                    continue;
+                } else if end > left {
+                    break;
+                }
+
+                if dx * dx + dy * dy <= self.radius * self.radius {
+                    if let Some(p) = self.tile(x, y) {
+                        visible.insert(p);
+                    }
+                }
+
+                let opaque = self.is_opaque(x, y);
+                if blocked {
+                    if opaque {
+                        next_start = right;
+                    } else {
+                        blocked = false;
+                        start = next_start;
+                    }
+                } else if opaque && distance < self.radius {
+                    blocked = true;
+                    self.cast(visible, distance + 1, start, left, octant);
+                    next_start = right;
+                }
+            }
+            if blocked {
+                break;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileVisibility {
+    Visible,
+    /// Seen before, but not right now.
+    Remembered,
+    Unseen,
+}
+
+/// What the player can see on a fog-of-war level, and what they have seen
+/// on every level so far. Both are kept only for the chunks the player has
+/// looked into.
+#[derive(Debug, Clone, Default)]
+pub struct Fog {
+    /// Whether the current level has fog at all.
+    pub enabled: bool,
+    level: i32,
+    visible: TileSet,
+    pub explored: HashMap<i32, TileSet>,
+    revision: u64,
+}
+
+impl Fog {
+    /// Looks again from `origin` on `level`, whose `sight` radius is used
+    /// unless `radius` is larger.
+    pub fn update(&mut self, level: &Level, origin: Point2<u32>, radius: u32) {
+        if (self.level, self.enabled) != (level.level, level.sight.is_some()) {
+            self.revision += 1;
+        }
+        self.level = level.level;
+        let sight = match level.sight {
+            Some(sight) => sight.max(radius),
+            None => {
+                self.enabled = false;
+                return;
+            }
+        };
+        self.enabled = true;
+        self.visible = field_of_view(level, origin, sight);
+
+        let explored = self.explored.entry(level.level).or_default();
+        let mut discovered = false;
+        for p in self.visible.iter() {
+            discovered |= explored.insert(p);
+        }
+        if discovered {
+            self.revision += 1;
+        }
+    }
+
+    /// Marks what's seen as out of date, for when the level has changed
+    /// under the player's feet.
+    pub fn invalidate(&mut self) {
+        self.revision += 1;
+    }
+
+    /// Changes whenever the level or the explored tiles do, so anything
+    /// drawn from the explored tiles knows to redraw.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn visibility(&self, p: Point2<u32>) -> TileVisibility {
+        if !self.enabled {
+            return TileVisibility::Visible;
+        }
+        if self.visible.contains(p) {
+            TileVisibility::Visible
+        } else if self
+            .explored
+            .get(&self.level)
+            .map_or(false, |explored| explored.contains(p))
+        {
+            TileVisibility::Remembered
+        } else {
+            TileVisibility::Unseen
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::LevelTile;
+
+    fn level(data: &str, sight: Option<u32>) -> Level {
+        let mut level = Level::from_ascii(1, "Test", data).unwrap();
+        level.sight = sight;
+        level
+    }
+
+    /// The field of view as ASCII rows, top row first: `o` for seen and `x`
+    /// for not.
+    fn seen(data: &str, origin: (u32, u32), radius: u32) -> Vec<String> {
+        let level = level(data, None);
+        let visible = field_of_view(&level, Point2::new(origin.0, origin.1), radius);
+        (0..level.height)
+            .rev()
+            .map(|y| {
+                (0..level.width)
+                    .map(|x| {
+                        if visible.contains(Point2::new(x as u32, y as u32)) {
+                            'o'
+                        } else {
+                            'x'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn open_ground_is_all_visible() {
+        assert_eq!(seen("...\n...\n...", (1, 1), 5), vec!["ooo", "ooo", "ooo"]);
+    }
+
+    #[test]
+    fn walls_and_fences_block_sight() {
+        assert_eq!(seen("..#..", (0, 0), 10), vec!["oooxx"]);
+        assert_eq!(seen("..-..", (4, 0), 10), vec!["xxooo"]);
+        // Grass, water and the like don't
+        assert_eq!(seen(".,%~^", (0, 0), 10), vec!["ooooo"]);
+    }
+
+    #[test]
+    fn shadows_spread_behind_walls() {
+        let fov = seen(".....\n.....\n..#..\n.....", (2, 0), 10);
+        // The shadow widens with distance from the wall
+        assert_eq!(fov, vec!["oxxxo", "ooxoo", "ooooo", "ooooo"]);
+    }
+
+    #[test]
+    fn radius_limits_sight() {
+        assert_eq!(seen(".......", (0, 0), 3), vec!["ooooxxx"]);
+    }
+
+    #[test]
+    fn remembers_explored_tiles() {
+        let level = level("...#...", Some(10));
+        let mut fog = Fog::default();
+        fog.update(&level, Point2::new(0, 0), 0);
+        assert_eq!(fog.visibility(Point2::new(1, 0)), TileVisibility::Visible);
+        assert_eq!(fog.visibility(Point2::new(5, 0)), TileVisibility::Unseen);
+
+        fog.update(&level, Point2::new(6, 0), 0);
+        assert_eq!(
+            fog.visibility(Point2::new(1, 0)),
+            TileVisibility::Remembered
+        );
+        assert_eq!(fog.visibility(Point2::new(5, 0)), TileVisibility::Visible);
+
+        // Other levels have their own memory, and no fog without a sight
+        let mut other = level.clone();
+        other.level = 2;
+        fog.update(&other, Point2::new(6, 0), 0);
+        assert_eq!(fog.visibility(Point2::new(1, 0)), TileVisibility::Unseen);
+        other.sight = None;
+        fog.update(&other, Point2::new(6, 0), 0);
+        assert_eq!(fog.visibility(Point2::new(1, 0)), TileVisibility::Visible);
+    }
+
+    #[test]
+    fn revision_follows_exploration() {
+        let level = level("...#...", Some(10));
+        let mut fog = Fog::default();
+        fog.update(&level, Point2::new(0, 0), 0);
+        let first = fog.revision();
+        fog.update(&level, Point2::new(1, 0), 0);
+        assert_eq!(fog.revision(), first);
+        fog.update(&level, Point2::new(6, 0), 0);
+        assert!(fog.revision() > first);
+    }
+
+    #[test]
+    fn fog_grows_with_what_is_seen_not_the_level() {
+        let mut level = Level::filled(1, "Huge", 16384, 16384, LevelTile::Plain);
+        level.sight = Some(8);
+        let mut fog = Fog::default();
+        fog.update(&level, Point2::new(8000, 8000), 0);
+        assert_eq!(
+            fog.visibility(Point2::new(8004, 8000)),
+            TileVisibility::Visible
+        );
+        assert_eq!(
+            fog.visibility(Point2::new(9000, 8000)),
+            TileVisibility::Unseen
+        );
+        assert!(fog.explored[&1].heap_bytes() < 4 * 1024);
+    }
+
+    #[test]
+    fn light_extends_sight() {
+        let level = level("........", Some(2));
+        let mut fog = Fog::default();
+        fog.update(&level, Point2::new(0, 0), 0);
+        assert_eq!(fog.visibility(Point2::new(4, 0)), TileVisibility::Unseen);
+        fog.update(&level, Point2::new(0, 0), 5);
+        assert_eq!(fog.visibility(Point2::new(4, 0)), TileVisibility::Visible);
+    }
+}
@@ -0,0 +1,348 @@
+use amethyst::core::math::{Point2, Point3, Vector2};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// Whether the player may step diagonally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MovementMode {
+    FourWay,
+    EightWay,
+}
+
+impl Default for MovementMode {
+    fn default() -> Self {
+        MovementMode::FourWay
+    }
+}
+
+/// How a level's edges join up. Walking off an edge that wraps comes back
+/// on at the opposite edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Topology {
+    Bounded,
+    /// East and west edges join, like a classic overworld.
+    WrapHorizontal,
+    /// East joins west and north joins south.
+    WrapBoth,
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Topology::Bounded
+    }
+}
+
+impl Topology {
+    pub fn wraps_x(self) -> bool {
+        self != Topology::Bounded
+    }
+
+    pub fn wraps_y(self) -> bool {
+        self == Topology::WrapBoth
+    }
+
+    /// The tile at signed coordinates `(x, y)` on a `width` by `height`
+    /// grid, brought back across any edges that join, or `None` if it's
+    /// past an edge that doesn't.
+    pub fn wrap(self, x: i64, y: i64, width: u32, height: u32) -> Option<Point2<u32>> {
+        let axis = |c: i64, size: u32, wraps: bool| {
+            let size = i64::from(size);
+            if wraps && size > 0 {
+                Some(c.rem_euclid(size) as u32)
+            } else if c >= 0 && c < size {
+                Some(c as u32)
+            } else {
+                None
+            }
+        };
+        Some(Point2::new(
+            axis(x, width, self.wraps_x())?,
+            axis(y, height, self.wraps_y())?,
+        ))
+    }
+
+    /// The shortest offset in tiles from `from` to `to` on a `width` by
+    /// `height` grid, going across joined edges when that's shorter.
+    pub fn offset(
+        self,
+        from: Point2<u32>,
+        to: Point2<u32>,
+        width: u32,
+        height: u32,
+    ) -> Vector2<i64> {
+        let axis = |from: u32, to: u32, size: u32, wraps: bool| {
+            let (d, size) = (i64::from(to) - i64::from(from), i64::from(size));
+            if !wraps || size == 0 {
+                d
+            } else if d > size / 2 {
+                d - size
+            } else if d < -size / 2 {
+                d + size
+            } else {
+                d
+            }
+        };
+        Vector2::new(
+            axis(from.x, to.x, width, self.wraps_x()),
+            axis(from.y, to.y, height, self.wraps_y()),
+        )
+    }
+}
+
+/// One of the eight directions a step on the tile grid can take. North is
+/// towards increasing `y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    pub const CARDINAL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    pub fn offset(self) -> Vector2<i32> {
+        match self {
+            Direction::North => Vector2::new(0, 1),
+            Direction::NorthEast => Vector2::new(1, 1),
+            Direction::East => Vector2::new(1, 0),
+            Direction::SouthEast => Vector2::new(1, -1),
+            Direction::South => Vector2::new(0, -1),
+            Direction::SouthWest => Vector2::new(-1, -1),
+            Direction::West => Vector2::new(-1, 0),
+            Direction::NorthWest => Vector2::new(-1, 1),
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::NorthEast => Direction::SouthWest,
+            Direction::East => Direction::West,
+            Direction::SouthEast => Direction::NorthWest,
+            Direction::South => Direction::North,
+            Direction::SouthWest => Direction::NorthEast,
+            Direction::West => Direction::East,
+            Direction::NorthWest => Direction::SouthEast,
+        }
+    }
+
+    pub fn is_diagonal(self) -> bool {
+        let offset = self.offset();
+        offset.x != 0 && offset.y != 0
+    }
+
+    /// The horizontal and vertical steps that make up a diagonal.
+    pub fn components(self) -> Option<(Direction, Direction)> {
+        match self {
+            Direction::NorthEast => Some((Direction::East, Direction::North)),
+            Direction::SouthEast => Some((Direction::East, Direction::South)),
+            Direction::SouthWest => Some((Direction::West, Direction::South)),
+            Direction::NorthWest => Some((Direction::West, Direction::North)),
+            _ => None,
+        }
+    }
+
+    /// Length of the step in tiles.
+    pub fn distance(self) -> f32 {
+        if self.is_diagonal() {
+            std::f32::consts::SQRT_2
+        } else {
+            1.0
+        }
+    }
+
+    /// The direction of the `east_west`/`north_south` axis values, with
+    /// east/west taking priority when both are pushed.
+    pub fn from_axes(x: f32, y: f32) -> Option<Self> {
+        if x > 0.0 {
+            Some(Direction::East)
+        } else if x < 0.0 {
+            Some(Direction::West)
+        } else if y > 0.0 {
+            Some(Direction::North)
+        } else if y < 0.0 {
+            Some(Direction::South)
+        } else {
+            None
+        }
+    }
+
+    /// Like `from_axes`, but in `EightWay` mode both axes count, picking the
+    /// nearest of the eight directions to the angle of the input.
+    pub fn from_axes_in(mode: MovementMode, x: f32, y: f32) -> Option<Self> {
+        if mode == MovementMode::FourWay || (x == 0.0 && y == 0.0) {
+            return Self::from_axes(x, y);
+        }
+        let sector = (y.atan2(x) / std::f32::consts::FRAC_PI_4).round() as i32;
+        Some(match sector.rem_euclid(8) {
+            0 => Direction::East,
+            1 => Direction::NorthEast,
+            2 => Direction::North,
+            3 => Direction::NorthWest,
+            4 => Direction::West,
+            5 => Direction::SouthWest,
+            6 => Direction::South,
+            _ => Direction::SouthEast,
+        })
+    }
+
+    /// The direction of a single step by `offset`, if it is one.
+    pub fn from_offset(offset: Vector2<i64>) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .cloned()
+            .find(|d| d.offset().map(i64::from) == offset)
+    }
+
+    /// The tile one step from `p`, or `None` if that would leave the
+    /// unsigned coordinate space.
+    pub fn step_from(self, p: Point3<u32>) -> Option<Point3<u32>> {
+        offset_point(p, self.offset())
+    }
+}
+
+/// Adds a signed offset to a tile coordinate, returning `None` on under- or
+/// overflow instead of wrapping.
+pub fn offset_point(p: Point3<u32>, offset: Vector2<i32>) -> Option<Point3<u32>> {
+    let x = u32::try_from(i64::from(p.x) + i64::from(offset.x)).ok()?;
+    let y = u32::try_from(i64::from(p.y) + i64::from(offset.y)).ok()?;
+    Some(Point3::new(x, y, p.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_in_all_four_directions() {
+        let p = Point3::new(5, 5, 0);
+        assert_eq!(Direction::North.step_from(p), Some(Point3::new(5, 6, 0)));
+        assert_eq!(Direction::East.step_from(p), Some(Point3::new(6, 5, 0)));
+        assert_eq!(Direction::South.step_from(p), Some(Point3::new(5, 4, 0)));
+        assert_eq!(Direction::West.step_from(p), Some(Point3::new(4, 5, 0)));
+    }
+
+    #[test]
+    fn steps_off_the_edge_are_none() {
+        let origin = Point3::new(0, 0, 0);
+        assert_eq!(Direction::West.step_from(origin), None);
+        assert_eq!(Direction::South.step_from(origin), None);
+        assert_eq!(Direction::North.step_from(origin), Some(Point3::new(0, 1, 0)));
+        assert_eq!(Direction::East.step_from(origin), Some(Point3::new(1, 0, 0)));
+
+        let corner = Point3::new(u32::max_value(), u32::max_value(), 0);
+        assert_eq!(Direction::East.step_from(corner), None);
+        assert_eq!(Direction::North.step_from(corner), None);
+    }
+
+    #[test]
+    fn opposite_undoes_step() {
+        let p = Point3::new(3, 3, 0);
+        for &dir in Direction::ALL.iter() {
+            let there = dir.step_from(p).unwrap();
+            assert_eq!(dir.opposite().step_from(there), Some(p));
+        }
+    }
+
+    #[test]
+    fn diagonal_steps() {
+        let p = Point3::new(5, 5, 0);
+        assert_eq!(Direction::NorthEast.step_from(p), Some(Point3::new(6, 6, 0)));
+        assert_eq!(Direction::SouthWest.step_from(p), Some(Point3::new(4, 4, 0)));
+        assert_eq!(Direction::SouthWest.step_from(Point3::new(0, 5, 0)), None);
+        assert_eq!(
+            Direction::NorthWest.components(),
+            Some((Direction::West, Direction::North))
+        );
+        assert_eq!(Direction::North.components(), None);
+        assert!((Direction::SouthEast.distance() - 1.414).abs() < 0.001);
+    }
+
+    #[test]
+    fn direction_between_neighbours() {
+        let p = Point2::new(5, 5);
+        let between = |q: Point3<u32>| {
+            Direction::from_offset(Topology::Bounded.offset(p, q.xy(), 10, 10))
+        };
+        for &dir in Direction::ALL.iter() {
+            assert_eq!(between(dir.step_from(Point3::new(5, 5, 0)).unwrap()), Some(dir));
+        }
+        assert_eq!(between(Point3::new(5, 5, 0)), None);
+        assert_eq!(between(Point3::new(7, 5, 0)), None);
+    }
+
+    #[test]
+    fn wrapping_edges() {
+        use Topology::*;
+        assert_eq!(Bounded.wrap(-1, 0, 10, 5), None);
+        assert_eq!(Bounded.wrap(9, 4, 10, 5), Some(Point2::new(9, 4)));
+        assert_eq!(WrapHorizontal.wrap(-1, 0, 10, 5), Some(Point2::new(9, 0)));
+        assert_eq!(WrapHorizontal.wrap(10, 2, 10, 5), Some(Point2::new(0, 2)));
+        assert_eq!(WrapHorizontal.wrap(3, 5, 10, 5), None);
+        assert_eq!(WrapBoth.wrap(23, -1, 10, 5), Some(Point2::new(3, 4)));
+        assert_eq!(WrapBoth.wrap(0, 0, 0, 0), None);
+    }
+
+    #[test]
+    fn shortest_offsets_cross_joined_edges() {
+        use Topology::*;
+        let (west, east) = (Point2::new(0, 0), Point2::new(9, 4));
+        assert_eq!(Bounded.offset(west, east, 10, 5), Vector2::new(9, 4));
+        assert_eq!(WrapHorizontal.offset(west, east, 10, 5), Vector2::new(-1, 4));
+        assert_eq!(WrapBoth.offset(west, east, 10, 5), Vector2::new(-1, -1));
+        assert_eq!(WrapBoth.offset(east, west, 10, 5), Vector2::new(1, 1));
+        assert_eq!(
+            Direction::from_offset(WrapHorizontal.offset(east, Point2::new(0, 4), 10, 5)),
+            Some(Direction::East)
+        );
+    }
+
+    #[test]
+    fn eight_way_axes() {
+        use MovementMode::*;
+        assert_eq!(Direction::from_axes_in(FourWay, 1.0, 1.0), Some(Direction::East));
+        assert_eq!(
+            Direction::from_axes_in(EightWay, 1.0, 1.0),
+            Some(Direction::NorthEast)
+        );
+        assert_eq!(
+            Direction::from_axes_in(EightWay, -1.0, -1.0),
+            Some(Direction::SouthWest)
+        );
+        assert_eq!(Direction::from_axes_in(EightWay, -0.9, 0.2), Some(Direction::West));
+        assert_eq!(Direction::from_axes_in(EightWay, 0.1, -0.9), Some(Direction::South));
+        assert_eq!(Direction::from_axes_in(EightWay, 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn axes_to_direction() {
+        assert_eq!(Direction::from_axes(1.0, 0.0), Some(Direction::East));
+        assert_eq!(Direction::from_axes(-1.0, 0.0), Some(Direction::West));
+        assert_eq!(Direction::from_axes(0.0, 1.0), Some(Direction::North));
+        assert_eq!(Direction::from_axes(0.0, -1.0), Some(Direction::South));
+        assert_eq!(Direction::from_axes(-1.0, 1.0), Some(Direction::West));
+        assert_eq!(Direction::from_axes(0.0, 0.0), None);
+    }
+}
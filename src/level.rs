@@ -1,10 +1,32 @@
-use amethyst::{core::math::Point2, Result};
-use ndarray::{Array, Array2, Ix2};
+use amethyst::{
+    assets::{Asset, Format, Handle, ProcessingState},
+    core::math::{Point2, Point3, Vector2},
+    ecs::VecStorage,
+    Result,
+};
 use nom::{branch::alt, character::complete::char, error::ErrorKind, multi::many_m_n, IResult};
 use serde::{Deserialize, Serialize};
-use std::{fmt, fs::File, io::BufReader, iter, path::Path};
+use serde_yaml::Value;
+use std::{
+    collections::{HashSet, VecDeque},
+    convert::TryFrom,
+    fmt,
+    fs::File,
+    io::{BufReader, Read},
+    iter,
+    path::Path,
+};
 
-use log::{debug, info};
+use log::debug;
+
+use crate::{
+    chunk::TileChunks,
+    clock::ScheduleEntry,
+    component::{LightSource, NpcGoal},
+    data::EncounterTable,
+    grid::{Direction, MovementMode, Topology},
+    lighting::AmbientLight,
+};
 
 #[cfg(profiler)]
 use thread_profiler::profile_scope;
@@ -14,44 +36,165 @@ pub struct Levels {
     levels: Vec<Level>,
 }
 
+/// A level as written in the YAML files, with its tiles as lines of ASCII.
+/// Fields equal to their defaults are left out.
 #[derive(Debug, PartialEq, Serialize, Deserialize, Default)]
 struct LevelData {
     level: i32,
     name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    movement: Option<MovementMode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    markers: Vec<Marker>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sight: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ambient: Option<AmbientLight>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encounters: Option<EncounterTable>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tilesets: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_bounded")]
+    topology: Topology,
     data: String,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize, Default)]
+fn is_bounded(topology: &Topology) -> bool {
+    *topology == Topology::Bounded
+}
+
+/// (De)serialized in the same format as the level files.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(into = "LevelData", try_from = "LevelData")]
 pub struct Level {
     pub level: i32,
     pub name: String,
     pub width: usize,
     pub height: usize,
-    data: Array2<LevelTile>,
+    /// Overrides the player's movement mode setting on this level.
+    pub movement: Option<MovementMode>,
+    /// Things placed on the map, such as NPCs.
+    pub markers: Vec<Marker>,
+    /// How far the player can see on levels with fog of war, `None` for
+    /// levels without.
+    pub sight: Option<u32>,
+    /// Light that changes with the time of day, `None` for levels that are
+    /// always fully lit.
+    pub ambient: Option<AmbientLight>,
+    /// Monsters that may attack as the player walks the level.
+    pub encounters: Option<EncounterTable>,
+    /// Names of the manifest tilesets the level is drawn with, the first
+    /// for any tile that doesn't name its own. The terrain sheet if empty.
+    pub tilesets: Vec<String>,
+    /// Which edges of the level join up.
+    pub topology: Topology,
+    data: TileChunks,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Marker {
+    pub position: (u32, u32),
+    pub kind: MarkerKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MarkerKind {
+    Npc {
+        name: String,
+        #[serde(default)]
+        goal: NpcGoal,
+        #[serde(default)]
+        schedule: Vec<ScheduleEntry>,
+    },
+    /// A pushable block.
+    Block,
+    PressurePlate {
+        flag: String,
+        #[serde(default)]
+        latch: bool,
+    },
+    /// A lamp or brazier lighting up the tiles around it.
+    Light(LightSource),
+    /// Stairs down to the next floor of a dungeon, or up where the party
+    /// arrived.
+    Stairs { down: bool },
+    /// Holds an item from the item lists, taken on stepping onto it.
+    Chest {
+        #[serde(default)]
+        item: Option<String>,
+    },
+    /// Random encounters on levels with zones only happen within `radius`
+    /// tiles of one.
+    EncounterZone { radius: u32 },
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, Copy)]
 pub enum LevelTile {
     Empty,
     Plain,
     Grass,
     Fence,
+    Road,
+    Swamp,
+    Water,
+    Lava,
+    Wall,
 }
 
 impl LevelTile {
+    pub const ALL: [LevelTile; 9] = [
+        LevelTile::Empty,
+        LevelTile::Plain,
+        LevelTile::Grass,
+        LevelTile::Fence,
+        LevelTile::Road,
+        LevelTile::Swamp,
+        LevelTile::Water,
+        LevelTile::Lava,
+        LevelTile::Wall,
+    ];
+
     pub fn new(c: char) -> Result<Self> {
         match c {
             '.' => Ok(Self::Plain),
             ',' => Ok(Self::Grass),
             ' ' => Ok(Self::Empty),
             '-' => Ok(Self::Fence),
+            '=' => Ok(Self::Road),
+            '~' => Ok(Self::Swamp),
+            '%' => Ok(Self::Water),
+            '^' => Ok(Self::Lava),
+            '#' => Ok(Self::Wall),
             _ => Err(amethyst::Error::new(LevelError::LevelLoadError)),
         }
     }
 
+    /// The character standing for the tile in level data.
+    pub fn symbol(self) -> char {
+        match self {
+            Self::Plain => '.',
+            Self::Grass => ',',
+            Self::Empty => ' ',
+            Self::Fence => '-',
+            Self::Road => '=',
+            Self::Swamp => '~',
+            Self::Water => '%',
+            Self::Lava => '^',
+            Self::Wall => '#',
+        }
+    }
+
+    /// Whether the tile hides what is behind it.
+    pub fn is_opaque(&self) -> bool {
+        match self {
+            Self::Fence | Self::Wall => true,
+            _ => false,
+        }
+    }
+
     pub fn is_blocking(&self) -> bool {
         match self {
-            Self::Fence | Self::Empty => true,
+            Self::Fence | Self::Wall | Self::Empty | Self::Water => true,
             _ => false,
         }
     }
@@ -61,6 +204,13 @@ impl LevelTile {
 pub enum LevelError {
     LevelLoadError,
     OutOfBoundsError,
+    /// A character in the `data:` block that isn't any tile, counting lines
+    /// and columns from 1.
+    UnknownTile {
+        symbol: char,
+        line: usize,
+        column: usize,
+    },
 }
 
 impl std::error::Error for LevelError {}
@@ -72,6 +222,15 @@ impl fmt::Display for LevelError {
         match *self {
             LevelLoadError => write!(fmt, "Failed load level data"),
             OutOfBoundsError => write!(fmt, "Index out of bounds"),
+            UnknownTile {
+                symbol,
+                line,
+                column,
+            } => write!(
+                fmt,
+                "Unknown tile `{}` on line {}, column {} of the level data",
+                symbol, line, column
+            ),
         }
     }
 }
@@ -85,38 +244,127 @@ impl Default for LevelTile {
 impl Level {
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = File::open(path)?;
-        let reader = BufReader::new(file);
+        Self::from_reader(BufReader::new(file))
+    }
 
-        // Read the JSON contents of the file as an instance of `User`.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        // Read the YAML contents of the file as an instance of `LevelData`.
         let u = serde_yaml::from_reader(reader);
         if let Err(e) = u {
             debug!("{:?}", e.location());
-            return Err(amethyst::Error::new(LevelError::LevelLoadError));
+            // Passed on as is, to say where in the file it went wrong
+            return Err(amethyst::Error::new(e));
         }
         let u: LevelData = u.expect("Level data accurate");
-        let lines = u.data.lines();
+        Self::try_from(u)
+    }
+
+    /// Builds a level from the ASCII `data:` block alone.
+    pub fn from_ascii(level: i32, name: &str, ascii: &str) -> Result<Self> {
+        let lines = ascii.lines();
         let width = lines
             .clone()
             .map(|l| l.chars().count())
             .fold(usize::min_value(), std::cmp::max);
         let height = lines.count();
 
-        let data = parse_level_string(width, height, &u.data).expect("Proper level parsing");
+        let rows = parse_level_string(width, ascii)?;
 
-        Ok(Self {
-            level: u.level,
-            name: u.name,
-            height: height,
-            width: width,
-            data: data,
-        })
+        let mut level = Self::filled(level, name, width, height, LevelTile::Empty);
+        // The last line is y = 0
+        for (y, row) in rows.into_iter().rev().enumerate() {
+            for (x, tile) in row.into_iter().enumerate() {
+                level.set_tile(Point2::new(x as u32, y as u32), tile);
+            }
+        }
+        level.data.compact();
+        debug!(
+            "{} is {} by {} tiles, held in {} bytes",
+            name,
+            width,
+            height,
+            level.data.heap_bytes()
+        );
+        Ok(level)
+    }
+
+    /// The level in the YAML format read by `from_reader`, with the tiles
+    /// as a block of ASCII below a legend of the characters used.
+    pub fn to_yaml(&self) -> Result<String> {
+        let ascii = self.to_ascii();
+        let mut data = serde_yaml::to_value(LevelData::from(self.clone()))?;
+        // Written out below by hand, so it stays one line per row
+        if let Value::Mapping(fields) = &mut data {
+            fields.remove(&Value::from("data"));
+        }
+        let mut yaml = serde_yaml::to_string(&data)?;
+        if !yaml.ends_with('\n') {
+            yaml.push('\n');
+        }
+
+        yaml.push_str("# Legend:\n");
+        let kinds = self.tile_kinds();
+        for tile in LevelTile::ALL.iter().filter(|tile| kinds.contains(tile)) {
+            yaml.push_str(&format!("#   '{}' {:?}\n", tile.symbol(), tile));
+        }
+        // Lines starting with empty tiles would throw off the indentation
+        let indent = if ascii.starts_with(' ') { "4" } else { "" };
+        yaml.push_str(&format!("data: |{}\n", indent));
+        for line in ascii.lines() {
+            yaml.push_str("    ");
+            yaml.push_str(line);
+            yaml.push('\n');
+        }
+        Ok(yaml)
+    }
+
+    /// The level's tiles as read by `from_ascii`, top line first.
+    pub fn to_ascii(&self) -> String {
+        let mut ascii = String::with_capacity((self.width + 1) * self.height);
+        for y in (0..self.height as u32).rev() {
+            for x in 0..self.width as u32 {
+                ascii.push(self.data.get(x, y).symbol());
+            }
+            ascii.push('\n');
+        }
+        ascii
+    }
+
+    /// A `width` by `height` level of nothing but `fill`.
+    pub fn filled(level: i32, name: &str, width: usize, height: usize, fill: LevelTile) -> Self {
+        Self {
+            level,
+            name: name.to_string(),
+            movement: None,
+            markers: Vec::new(),
+            sight: None,
+            ambient: None,
+            encounters: None,
+            tilesets: Vec::new(),
+            topology: Topology::default(),
+            height,
+            width,
+            data: TileChunks::new(fill),
+        }
     }
 
     pub fn get_tile(&self, p: Point2<u32>) -> Result<LevelTile> {
-        if !self.in_bounds(p) {
-            return Err(amethyst::Error::new(LevelError::OutOfBoundsError));
+        match self.wrap(i64::from(p.x), i64::from(p.y)) {
+            Some(p) => Ok(self.data.get(p.x, p.y)),
+            None => Err(amethyst::Error::new(LevelError::OutOfBoundsError)),
         }
-        Ok(self.data[(p.x as usize, p.y as usize)])
+    }
+
+    /// Changes the tile at `p`, if it is on the level.
+    pub fn set_tile(&mut self, p: Point2<u32>, tile: LevelTile) {
+        if let Some(p) = self.wrap(i64::from(p.x), i64::from(p.y)) {
+            self.data.set(p.x, p.y, tile);
+        }
+    }
+
+    /// Every kind of tile on the level.
+    pub fn tile_kinds(&self) -> HashSet<LevelTile> {
+        self.data.kinds()
     }
 
     pub fn is_blocking(&self, p: Point2<u32>) -> bool {
@@ -128,19 +376,205 @@ impl Level {
         }
     }
 
+    /// Whether random encounters can happen at `p`: anywhere on levels
+    /// without encounter zones, and only within one on levels with.
+    pub fn in_encounter_zone(&self, p: Point2<u32>) -> bool {
+        let mut zones = self
+            .markers
+            .iter()
+            .filter_map(|marker| match marker.kind {
+                MarkerKind::EncounterZone { radius } => Some((marker.position, radius)),
+                _ => None,
+            })
+            .peekable();
+        if zones.peek().is_none() {
+            return true;
+        }
+        zones.any(|((x, y), radius)| {
+            let offset = self.offset(Point2::new(x, y), p);
+            offset.x * offset.x + offset.y * offset.y <= i64::from(radius * radius)
+        })
+    }
+
+    /// The open tile nearest `p`, or nearest the edge of the level if `p` is
+    /// off it. `None` if there are no open tiles at all.
+    pub fn nearest_open(&self, p: Point2<u32>) -> Option<Point2<u32>> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let start = Point3::new(
+            p.x.min(self.width as u32 - 1),
+            p.y.min(self.height as u32 - 1),
+            0,
+        );
+        let mut seen: HashSet<Point3<u32>> = iter::once(start).collect();
+        let mut queue: VecDeque<Point3<u32>> = iter::once(start).collect();
+        while let Some(p) = queue.pop_front() {
+            if !self.is_blocking(p.xy()) {
+                return Some(p.xy());
+            }
+            for &direction in Direction::CARDINAL.iter() {
+                if let Some(next) = self.neighbour(p, direction) {
+                    if seen.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether `p` names a tile on the level. Along edges that wrap, every
+    /// coordinate does.
     pub fn in_bounds(&self, p: Point2<u32>) -> bool {
-        !(p.x >= self.width as u32 || p.y >= self.height as u32)
+        self.wrap(i64::from(p.x), i64::from(p.y)).is_some()
+    }
+
+    /// The tile at signed coordinates `(x, y)`, brought back onto the level
+    /// across edges that wrap.
+    pub fn wrap(&self, x: i64, y: i64) -> Option<Point2<u32>> {
+        self.topology
+            .wrap(x, y, self.width as u32, self.height as u32)
+    }
+
+    /// The shortest offset in tiles from `from` to `to`, across edges that
+    /// wrap when that's shorter.
+    pub fn offset(&self, from: Point2<u32>, to: Point2<u32>) -> Vector2<i64> {
+        self.topology
+            .offset(from, to, self.width as u32, self.height as u32)
+    }
+
+    /// The tile next to `p` in `direction`, whether or not it's open.
+    pub fn neighbour(&self, p: Point3<u32>, direction: Direction) -> Option<Point3<u32>> {
+        let offset = direction.offset();
+        let next = self.wrap(
+            i64::from(p.x) + i64::from(offset.x),
+            i64::from(p.y) + i64::from(offset.y),
+        )?;
+        Some(Point3::new(next.x, next.y, p.z))
+    }
+
+    /// The tile reached by stepping from `p` in `direction`, if it is open.
+    /// Diagonal steps may not squeeze between two blocking tiles.
+    pub fn step(&self, p: Point3<u32>, direction: Direction) -> Option<Point3<u32>> {
+        let target = self.neighbour(p, direction)?;
+        if self.is_blocking(target.xy()) {
+            return None;
+        }
+        if let Some((horizontal, vertical)) = direction.components() {
+            let blocked = |d: Direction| {
+                self.neighbour(p, d)
+                    .map(|q| self.is_blocking(q.xy()))
+                    .unwrap_or(true)
+            };
+            if blocked(horizontal) && blocked(vertical) {
+                return None;
+            }
+        }
+        Some(target)
+    }
+}
+
+/// Levels are equal when they have the same tiles, however they happen to
+/// be stored.
+impl PartialEq for Level {
+    fn eq(&self, other: &Self) -> bool {
+        self.level == other.level
+            && self.name == other.name
+            && (self.width, self.height) == (other.width, other.height)
+            && self.movement == other.movement
+            && self.markers == other.markers
+            && self.sight == other.sight
+            && self.ambient == other.ambient
+            && self.encounters == other.encounters
+            && self.tilesets == other.tilesets
+            && self.topology == other.topology
+            && (0..self.height as u32).all(|y| {
+                (0..self.width as u32).all(|x| self.data.get(x, y) == other.data.get(x, y))
+            })
+    }
+}
+
+impl From<Level> for LevelData {
+    fn from(level: Level) -> Self {
+        Self {
+            data: level.to_ascii(),
+            level: level.level,
+            name: level.name,
+            movement: level.movement,
+            markers: level.markers,
+            sight: level.sight,
+            ambient: level.ambient,
+            encounters: level.encounters,
+            tilesets: level.tilesets,
+            topology: level.topology,
+        }
     }
 }
 
-fn parse_level_string(width: usize, height: usize, data: &str) -> Result<Array<LevelTile, Ix2>> {
+impl TryFrom<LevelData> for Level {
+    type Error = amethyst::Error;
+
+    fn try_from(data: LevelData) -> Result<Self> {
+        let mut level = Self::from_ascii(data.level, &data.name, &data.data)?;
+        level.movement = data.movement;
+        level.markers = data.markers;
+        level.sight = data.sight;
+        level.ambient = data.ambient;
+        level.encounters = data.encounters;
+        level.tilesets = data.tilesets;
+        level.topology = data.topology;
+        Ok(level)
+    }
+}
+
+impl Asset for Level {
+    const NAME: &'static str = "dd::Level";
+    type Data = Self;
+    type HandleStorage = VecStorage<Handle<Self>>;
+}
+
+impl From<Level> for Result<ProcessingState<Level>> {
+    fn from(level: Level) -> Result<ProcessingState<Level>> {
+        Ok(ProcessingState::Loaded(level))
+    }
+}
+
+/// Loads a `Level` from the YAML/ASCII format read by `Level::from_file`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LevelFormat;
+
+impl Format<Level> for LevelFormat {
+    fn name(&self) -> &'static str {
+        "LevelFormat"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<Level> {
+        Level::from_reader(bytes.as_slice())
+    }
+}
+
+/// The tiles on each line of `data`, top line first, padded out to `width`.
+fn parse_level_string(width: usize, data: &str) -> Result<Vec<Vec<LevelTile>>> {
     #[cfg(profiler)]
     profile_scope!("parse_level_string");
-    let lines = data.lines().map(line_parser(width)).map(|t| t.unwrap().1);
-    let v: Vec<LevelTile> = lines.rev().flatten().collect();
-    let array = Array::from_shape_vec((height, width), v)?.reversed_axes();
-    info!("{:?}", array);
-    Ok(array)
+    let parse_line = line_parser(width);
+    data.lines()
+        .enumerate()
+        .map(|(line, text)| {
+            let (rest, tiles) =
+                parse_line(text).map_err(|_| amethyst::Error::new(LevelError::LevelLoadError))?;
+            // Parsing stops short at the first character that isn't a tile
+            match rest.chars().next() {
+                Some(symbol) => Err(amethyst::Error::new(LevelError::UnknownTile {
+                    symbol,
+                    line: line + 1,
+                    column: text.chars().count() - rest.chars().count() + 1,
+                })),
+                None => Ok(tiles),
+            }
+        })
+        .collect()
 }
 
 fn line_parser(width: usize) -> impl Fn(&str) -> IResult<&str, Vec<LevelTile>> {
@@ -154,7 +588,17 @@ fn line_parser(width: usize) -> impl Fn(&str) -> IResult<&str, Vec<LevelTile>> {
 }
 
 fn tile_parser(i: &str) -> IResult<&str, LevelTile> {
-    let (r, t) = alt((char('.'), char(' '), char(','), char('-')))(i)?;
+    let (r, t) = alt((
+        char('.'),
+        char(' '),
+        char(','),
+        char('-'),
+        char('='),
+        char('~'),
+        char('%'),
+        char('^'),
+        char('#'),
+    ))(i)?;
     let tile =
         LevelTile::new(t).map_err(|_| nom::Err::Failure(("Error parsing", ErrorKind::Char)))?;
     Ok((r, tile))
@@ -163,7 +607,398 @@ fn tile_parser(i: &str) -> IResult<&str, LevelTile> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        chunk::tests::islands,
+        data::Encounter,
+        dungeon::{self, Algorithm, DungeonConfig, DungeonFloor},
+    };
+    use proptest::prelude::*;
+
+    fn test_level(data: &str) -> Level {
+        Level::from_ascii(1, "Test", data).expect("test level should parse")
+    }
+
+    #[test]
+    fn test_load_level() {
+        let yaml = "level: 1\nname: Test\ndata: |\n    ..,\n    .-.\n";
+        assert_eq!(
+            Level::from_reader(yaml.as_bytes()).unwrap(),
+            test_level("..,\n.-.")
+        );
+
+        let level = test_level("..,\n.-.");
+        assert_eq!((level.width, level.height), (3, 2));
+        // The last line of the data is y = 0
+        assert_eq!(level.get_tile(Point2::new(2, 1)).unwrap(), LevelTile::Grass);
+        assert_eq!(level.get_tile(Point2::new(1, 0)).unwrap(), LevelTile::Fence);
+        assert!(level.get_tile(Point2::new(3, 0)).is_err());
+
+        let level = test_level("=~%^#");
+        let tiles: Vec<_> = (0..5)
+            .map(|x| level.get_tile(Point2::new(x, 0)).unwrap())
+            .collect();
+        use LevelTile::*;
+        assert_eq!(tiles, vec![Road, Swamp, Water, Lava, Wall]);
+        assert!(level.is_blocking(Point2::new(2, 0)));
+        assert!(!level.is_blocking(Point2::new(3, 0)));
+    }
+
+    #[test]
+    fn test_steps_at_map_edges() {
+        let level = test_level("...\n...");
+        let origin = Point3::new(0, 0, 0);
+        assert_eq!(Direction::West.step_from(origin), None);
+        assert_eq!(Direction::South.step_from(origin), None);
+
+        let east = Direction::East.step_from(Point3::new(2, 0, 0)).unwrap();
+        assert!(level.is_blocking(east.xy()));
+        let north = Direction::North.step_from(Point3::new(0, 1, 0)).unwrap();
+        assert!(level.is_blocking(north.xy()));
+
+        let inside = Direction::North.step_from(origin).unwrap();
+        assert!(!level.is_blocking(inside.xy()));
+    }
+
+    #[test]
+    fn test_wrapping_edges() {
+        let yaml = "level: 3\nname: World\ntopology: WrapHorizontal\ndata: |\n    .#.\n    ..,\n";
+        let level = Level::from_reader(yaml.as_bytes()).unwrap();
+        assert_eq!(level.topology, Topology::WrapHorizontal);
+        assert_eq!(level.get_tile(Point2::new(5, 0)).unwrap(), LevelTile::Grass);
+        assert!(level.in_bounds(Point2::new(7, 1)));
+        assert!(!level.in_bounds(Point2::new(0, 2)));
+
+        // Off the east edge and back on at the west
+        let east = Point3::new(2, 0, 0);
+        assert_eq!(
+            level.step(east, Direction::East),
+            Some(Point3::new(0, 0, 0))
+        );
+        assert_eq!(
+            level.step(Point3::new(0, 1, 0), Direction::West),
+            Some(Point3::new(2, 1, 0))
+        );
+        assert_eq!(level.step(east, Direction::North), Some(Point3::new(2, 1, 0)));
+        assert_eq!(level.step(east, Direction::South), None);
+        assert_eq!(
+            level.offset(Point2::new(2, 0), Point2::new(0, 0)),
+            Vector2::new(1, 0)
+        );
+    }
+
+    #[test]
+    fn test_diagonal_corner_cutting() {
+        // No squeezing between the two fences
+        let level = test_level("-.\n.-");
+        assert_eq!(level.step(Point3::new(0, 0, 0), Direction::NorthEast), None);
+        assert_eq!(level.step(Point3::new(1, 1, 0), Direction::SouthWest), None);
+
+        // Past a single fence is fine, onto one is not
+        let level = test_level("...\n-..\n...");
+        assert_eq!(
+            level.step(Point3::new(0, 0, 0), Direction::NorthEast),
+            Some(Point3::new(1, 1, 0))
+        );
+        assert_eq!(level.step(Point3::new(1, 0, 0), Direction::NorthWest), None);
+        assert_eq!(
+            level.step(Point3::new(1, 2, 0), Direction::SouthEast),
+            Some(Point3::new(2, 1, 0))
+        );
+        assert_eq!(level.step(Point3::new(0, 0, 0), Direction::SouthWest), None);
+    }
+
+    #[test]
+    fn test_movement_override() {
+        let yaml = "level: 2\nname: Open\nmovement: EightWay\ndata: |\n    ...\n";
+        let level = Level::from_reader(yaml.as_bytes()).unwrap();
+        assert_eq!(level.movement, Some(MovementMode::EightWay));
+        assert_eq!(test_level("...").movement, None);
+    }
+
+    #[test]
+    fn test_big_levels_cost_what_is_on_them() {
+        let small = islands(256);
+        let huge = islands(8192);
+        assert_eq!(small.data.heap_bytes(), huge.data.heap_bytes());
+        assert_eq!(
+            huge.get_tile(Point2::new(8000, 8000)).unwrap(),
+            LevelTile::Water
+        );
+    }
 
     #[test]
-    fn test_load_level() {}
+    fn test_nearest_open_tile() {
+        let level = test_level("##.\n###\n.##");
+        assert_eq!(level.nearest_open(Point2::new(2, 2)), Some(Point2::new(2, 2)));
+        assert_eq!(level.nearest_open(Point2::new(0, 1)), Some(Point2::new(0, 0)));
+        assert_eq!(level.nearest_open(Point2::new(9, 9)), Some(Point2::new(2, 2)));
+        assert_eq!(test_level("#").nearest_open(Point2::new(0, 0)), None);
+    }
+
+    #[test]
+    fn test_unknown_tiles_are_errors() {
+        let err = Level::from_ascii(1, "Test", "...\n.x.").unwrap_err();
+        assert!(err.to_string().contains("`x` on line 2, column 2"));
+        let yaml = "level: 1\nname: Test\ndata: |\n  ..?\n";
+        assert!(Level::from_reader(yaml.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_encounter_zones() {
+        let mut level = test_level(".........\n.........");
+        assert!(level.in_encounter_zone(Point2::new(8, 0)));
+
+        level.markers.push(Marker {
+            position: (1, 0),
+            kind: MarkerKind::EncounterZone { radius: 2 },
+        });
+        assert!(level.in_encounter_zone(Point2::new(3, 0)));
+        assert!(level.in_encounter_zone(Point2::new(2, 1)));
+        assert!(!level.in_encounter_zone(Point2::new(3, 1)));
+        assert!(!level.in_encounter_zone(Point2::new(8, 0)));
+
+        // Measured the short way round on levels that wrap
+        level.topology = Topology::WrapHorizontal;
+        assert!(level.in_encounter_zone(Point2::new(8, 0)));
+    }
+
+    #[test]
+    fn test_writes_what_it_reads() {
+        let yaml = "level: 4\nname: Cellar\nsight: 5\nmarkers:\n  - position: [1, 0]\n    kind:\n      Chest:\n        item: Potion\ndata: |\n    #.,#\n    .%^ \n";
+        let level = Level::from_reader(yaml.as_bytes()).unwrap();
+        assert_eq!(level.to_ascii(), "#.,#\n.%^ \n");
+        let written = level.to_yaml().unwrap();
+        assert!(written.ends_with(
+            "# Legend:\n#   ' ' Empty\n#   '.' Plain\n#   ',' Grass\n#   '%' Water\n#   '^' Lava\n#   '#' Wall\ndata: |\n    #.,#\n    .%^ \n"
+        ));
+        assert!(!written.contains("movement"));
+        assert_eq!(Level::from_reader(written.as_bytes()).unwrap(), level);
+
+        // Empty tiles down the left can't be mistaken for indentation
+        let level = test_level("  .\n   \n # ");
+        let written = level.to_yaml().unwrap();
+        assert_eq!(Level::from_reader(written.as_bytes()).unwrap(), level);
+    }
+
+    fn tile() -> impl Strategy<Value = LevelTile> {
+        prop::sample::select(LevelTile::ALL.to_vec())
+    }
+
+    fn colour() -> impl Strategy<Value = (f32, f32, f32)> {
+        (0.0f32..1.0, 0.0f32..1.0, 0.0f32..1.0)
+    }
+
+    /// A marker of any kind somewhere on a `width` by `height` level.
+    fn marker(width: u32, height: u32) -> impl Strategy<Value = Marker> {
+        let position = (0..width, 0..height);
+        let kind = prop_oneof![
+            ("\\PC{0,12}", position.clone(), 0.0f32..24.0).prop_map(|(name, to, from)| {
+                MarkerKind::Npc {
+                    name,
+                    goal: NpcGoal::WalkTo(to),
+                    schedule: vec![ScheduleEntry {
+                        from,
+                        goal: NpcGoal::ChasePlayer,
+                    }],
+                }
+            }),
+            Just(MarkerKind::Block),
+            any::<bool>().prop_map(|latch| MarkerKind::PressurePlate {
+                flag: "plate".to_string(),
+                latch,
+            }),
+            (0.0f32..8.0, colour())
+                .prop_map(|(radius, color)| MarkerKind::Light(LightSource { radius, color })),
+            any::<bool>().prop_map(|down| MarkerKind::Stairs { down }),
+            proptest::option::of(Just("Potion".to_string()))
+                .prop_map(|item| MarkerKind::Chest { item }),
+            (0u32..10).prop_map(|radius| MarkerKind::EncounterZone { radius }),
+        ];
+        (position, kind).prop_map(|(position, kind)| Marker { position, kind })
+    }
+
+    prop_compose! {
+        /// A level with random tiles and a bit of everything else.
+        fn random_level()(width in 1usize..40, height in 1usize..40)(
+            width in Just(width),
+            height in Just(height),
+            number in -50i32..50,
+            name in "\\PC{0,20}",
+            tiles in prop::collection::vec(tile(), width * height),
+            markers in prop::collection::vec(marker(width as u32, height as u32), 0..6),
+            movement in proptest::option::of(Just(MovementMode::EightWay)),
+            sight in proptest::option::of(0u32..12),
+            ambient in proptest::option::of((colour(), colour())),
+            encounters in proptest::option::of((0.0f32..1.0, 0u32..5)),
+            tilesets in any::<bool>(),
+            topology in prop::sample::select(vec![
+                Topology::Bounded,
+                Topology::WrapHorizontal,
+                Topology::WrapBoth,
+            ]),
+        ) -> Level {
+            let mut level = Level::filled(number, &name, width, height, LevelTile::Empty);
+            for (i, &tile) in tiles.iter().enumerate() {
+                level.set_tile(Point2::new((i % width) as u32, (i / width) as u32), tile);
+            }
+            level.markers = markers;
+            level.movement = movement;
+            level.sight = sight;
+            level.ambient = ambient.map(|(day, night)| AmbientLight { day, night });
+            level.encounters = encounters.map(|(rate, weight)| EncounterTable {
+                rate,
+                monsters: vec![Encounter {
+                    monster: "Slime".to_string(),
+                    weight,
+                    times: vec![],
+                }],
+            });
+            if tilesets {
+                level.tilesets = vec!["cave".to_string(), "terrain".to_string()];
+            }
+            level.topology = topology;
+            level
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_random_levels_round_trip(level in random_level()) {
+            let written = level.to_yaml().unwrap();
+            let read = Level::from_reader(written.as_bytes()).ok();
+            prop_assert_eq!(read.as_ref(), Some(&level), "{}", written);
+
+            // Through serde too, as part of something bigger
+            let serialized = serde_yaml::to_string(&vec![level.clone()]).unwrap();
+            let deserialized: Vec<Level> = serde_yaml::from_str(&serialized).unwrap();
+            prop_assert_eq!(deserialized, vec![level]);
+        }
+    }
+
+    #[test]
+    fn test_generated_levels_round_trip() {
+        for &algorithm in &[Algorithm::Rooms, Algorithm::Caves, Algorithm::Bsp] {
+            let config = DungeonConfig {
+                algorithm,
+                ..DungeonConfig::default()
+            };
+            for seed in 0..10 {
+                let level = dungeon::generate(&config, DungeonFloor::new(seed));
+                let written = level.to_yaml().unwrap();
+                assert_eq!(Level::from_reader(written.as_bytes()).unwrap(), level);
+            }
+        }
+    }
+
+    #[test]
+    fn test_markers() {
+        let yaml = "
+level: 1
+name: Test
+markers:
+  - position: [1, 0]
+    kind:
+      Npc:
+        name: Villager
+        goal:
+          WalkTo: [0, 0]
+  - position: [0, 0]
+    kind:
+      Npc:
+        name: Dog
+data: |
+    ..
+";
+        let level = Level::from_reader(yaml.as_bytes()).unwrap();
+        assert_eq!(
+            level.markers,
+            vec![
+                Marker {
+                    position: (1, 0),
+                    kind: MarkerKind::Npc {
+                        name: "Villager".to_string(),
+                        goal: NpcGoal::WalkTo((0, 0)),
+                        schedule: vec![],
+                    },
+                },
+                Marker {
+                    position: (0, 0),
+                    kind: MarkerKind::Npc {
+                        name: "Dog".to_string(),
+                        goal: NpcGoal::Idle,
+                        schedule: vec![],
+                    },
+                },
+            ]
+        );
+        assert!(test_level("..").markers.is_empty());
+
+        let yaml = "
+level: 1
+name: Test
+markers:
+  - position: [0, 0]
+    kind:
+      Npc:
+        name: Villager
+        schedule:
+          - from: 7.0
+            goal:
+              WalkTo: [2, 0]
+          - from: 19.0
+            goal: Idle
+  - position: [1, 0]
+    kind: Block
+  - position: [2, 0]
+    kind:
+      PressurePlate:
+        flag: first_plate
+        latch: true
+  - position: [0, 1]
+    kind:
+      Stairs:
+        down: true
+  - position: [1, 1]
+    kind:
+      Chest:
+        item: Potion
+ambient:
+  day: [1.0, 1.0, 1.0]
+  night: [0.2, 0.25, 0.45]
+encounters:
+  rate: 0.05
+  monsters:
+    - monster: Slime
+      weight: 3
+    - monster: Bat
+      times: [Dusk, Night]
+    - monster: Skeleton
+      times: [Night]
+data: |
+    ...
+    ...
+";
+        let level = Level::from_reader(yaml.as_bytes()).unwrap();
+        assert_eq!(level.markers.len(), 5);
+        match &level.markers[0].kind {
+            MarkerKind::Npc { schedule, .. } => assert_eq!(schedule.len(), 2),
+            kind => panic!("Expected an NPC, got {:?}", kind),
+        }
+        assert_eq!(
+            level.markers[1..].iter().map(|m| &m.kind).collect::<Vec<_>>(),
+            vec![
+                &MarkerKind::Block,
+                &MarkerKind::PressurePlate {
+                    flag: "first_plate".to_string(),
+                    latch: true,
+                },
+                &MarkerKind::Stairs { down: true },
+                &MarkerKind::Chest {
+                    item: Some("Potion".to_string()),
+                },
+            ]
+        );
+        assert!(level.ambient.is_some());
+        assert_eq!(level.encounters.map(|table| table.monsters.len()), Some(3));
+    }
 }
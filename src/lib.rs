@@ -0,0 +1,27 @@
+pub mod animation;
+pub mod assets;
+pub mod autotile;
+pub mod bindings;
+pub mod camera;
+pub mod chunk;
+pub mod clock;
+pub mod component;
+pub mod controls;
+pub mod data;
+pub mod dungeon;
+pub mod events;
+pub mod fov;
+pub mod grid;
+pub mod level;
+pub mod lighting;
+pub mod minimap;
+pub mod party;
+pub mod pathfinding;
+pub mod puzzle;
+pub mod rng;
+pub mod save;
+pub mod settings;
+pub mod states;
+pub mod story;
+pub mod system;
+pub mod tileset;
@@ -0,0 +1,110 @@
+use amethyst::core::math::Point2;
+use serde::{Deserialize, Serialize};
+
+use crate::component::LightSource;
+
+/// A level's light with the sun up and down, as red, green and blue
+/// multipliers on everything drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AmbientLight {
+    pub day: (f32, f32, f32),
+    pub night: (f32, f32, f32),
+}
+
+impl AmbientLight {
+    /// Blends from the night light to the day light as `daylight` goes from
+    /// 0 to 1.
+    pub fn at(&self, daylight: f32) -> (f32, f32, f32) {
+        let t = daylight.max(0.0).min(1.0);
+        let blend = |night: f32, day: f32| night + (day - night) * t;
+        (
+            blend(self.night.0, self.day.0),
+            blend(self.night.1, self.day.1),
+            blend(self.night.2, self.day.2),
+        )
+    }
+}
+
+/// The ambient light and where the lights are this frame, kept up to date
+/// by `LightingSystem`.
+#[derive(Debug, Clone, Default)]
+pub struct Lighting {
+    /// `None` on levels that are always fully lit.
+    pub ambient: Option<(f32, f32, f32)>,
+    /// Each light and the tile it's on, fractional while it moves.
+    pub lights: Vec<(Point2<f32>, LightSource)>,
+}
+
+impl Lighting {
+    /// The colour to tint whatever is drawn on tile `p`. Lights add to the
+    /// ambient light, fading out towards their radius.
+    pub fn light_at(&self, p: Point2<f32>) -> (f32, f32, f32) {
+        let ambient = match self.ambient {
+            Some(ambient) => ambient,
+            None => return (1.0, 1.0, 1.0),
+        };
+        let (mut r, mut g, mut b) = ambient;
+        for (at, light) in &self.lights {
+            let distance = (at - p).norm();
+            if distance >= light.radius {
+                continue;
+            }
+            let strength = 1.0 - distance / light.radius;
+            r += light.color.0 * strength;
+            g += light.color.1 * strength;
+            b += light.color.2 * strength;
+        }
+        (r.min(1.0), g.min(1.0), b.min(1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AMBIENT: AmbientLight = AmbientLight {
+        day: (1.0, 1.0, 1.0),
+        night: (0.2, 0.2, 0.4),
+    };
+
+    fn close(a: (f32, f32, f32), b: (f32, f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 0.001 && (a.1 - b.1).abs() < 0.001 && (a.2 - b.2).abs() < 0.001
+    }
+
+    #[test]
+    fn ambient_blends_with_daylight() {
+        assert!(close(AMBIENT.at(1.0), (1.0, 1.0, 1.0)));
+        assert!(close(AMBIENT.at(0.0), (0.2, 0.2, 0.4)));
+        assert!(close(AMBIENT.at(0.5), (0.6, 0.6, 0.7)));
+    }
+
+    #[test]
+    fn lights_fade_with_distance() {
+        let lighting = Lighting {
+            ambient: Some(AMBIENT.at(0.0)),
+            lights: vec![(
+                Point2::new(2.0, 2.0),
+                LightSource {
+                    radius: 4.0,
+                    color: (1.0, 0.8, 0.4),
+                },
+            )],
+        };
+        assert!(close(
+            lighting.light_at(Point2::new(2.0, 2.0)),
+            (1.0, 1.0, 0.8)
+        ));
+        assert!(close(
+            lighting.light_at(Point2::new(4.0, 2.0)),
+            (0.7, 0.6, 0.6)
+        ));
+        assert!(close(
+            lighting.light_at(Point2::new(9.0, 2.0)),
+            (0.2, 0.2, 0.4)
+        ));
+
+        // Levels without ambient light are never darkened
+        let lit = Lighting::default();
+        assert!(close(lit.light_at(Point2::new(9.0, 2.0)), (1.0, 1.0, 1.0)));
+    }
+}
@@ -1,6 +1,8 @@
 use amethyst::{
+    assets::{HotReloadBundle, HotReloadStrategy},
+    audio::AudioBundle,
     core::transform::TransformBundle,
-    input::{InputBundle, StringBindings},
+    input::{Bindings, InputBundle, StringBindings},
     prelude::*,
     renderer::{
         plugins::{RenderFlat2D, RenderToWindow},
@@ -10,6 +12,7 @@ use amethyst::{
     tiles::{MortonEncoder2D, RenderTiles2D},
     ui::{RenderUi, UiBundle},
     utils::{application_root_dir, ortho_camera::CameraOrthoSystem},
+    window::DisplayConfig,
     LogLevelFilter, Logger,
 };
 #[cfg(feature = "inspector")]
@@ -24,15 +27,14 @@ use amethyst_imgui::RenderImgui;
 use chrono::Duration;
 use std::time::Instant;
 
-mod component;
-mod events;
-mod level;
-mod states;
-mod system;
-
-use crate::{
+use dd::{
+    camera::CameraConfig,
+    clock::ClockConfig,
+    dungeon::{self, DungeonConfig},
     events::{GameStateEvent, GameStateEventReader},
+    settings::Settings,
     states::{game::GameTile, loading::Loading},
+    story::VictoryCondition,
     system::GameBundle,
 };
 
@@ -74,24 +76,44 @@ inspector![
 ];
 
 fn main() -> amethyst::Result<()> {
-    setup_logging()?;
     let app_root = application_root_dir()?;
-
     let resources = app_root.join("resources");
-    let display_config = resources.join("display_config.ron");
+    let dungeon = DungeonConfig::load(resources.join("dungeon.ron"))?;
+
+    // `dd generate ...` writes out a random dungeon instead of playing, with
+    // nothing logged over the level on standard output
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("generate") {
+        return dungeon::export(&args[1..], dungeon);
+    }
+
+    setup_logging()?;
+    let settings = Settings::load_user();
+
+    let mut display_config = DisplayConfig::load(resources.join("display_config.ron"))?;
+    settings.apply_display_config(&mut display_config);
 
-    let input_config = resources.join("input_bindings.ron");
+    let bindings = Bindings::<StringBindings>::load(resources.join("input_bindings.ron"))?;
+    let input_bundle =
+        InputBundle::<StringBindings>::new().with_bindings(settings.bindings_over(bindings));
+    let victory = VictoryCondition::load(resources.join("victory.ron"))?;
+    let camera = CameraConfig::load(resources.join("camera.ron"))?;
+    let clock = ClockConfig::load(resources.join("clock.ron"))?;
 
     let game_data = GameDataBuilder::default()
         .with(CameraOrthoSystem::default(), "camera_ortho", &[])
         .with_bundle(TransformBundle::new())?
         .with_bundle(UiBundle::<StringBindings>::new())?
-        .with_bundle(InputBundle::<StringBindings>::new().with_bindings_from_file(input_config)?)?
+        .with_bundle(input_bundle)?
+        .with_bundle(AudioBundle::default())?
+        // Checks the loaded levels and data files for changes every 30
+        // frames, for `ReloadSystem` to swap in
+        .with_bundle(HotReloadBundle::new(HotReloadStrategy::every(30)))?
         .with_bundle(GameBundle)?
         .with_bundle(
             RenderingBundle::<DefaultBackend>::new()
                 .with_plugin(
-                    RenderToWindow::from_config_path(display_config)?
+                    RenderToWindow::from_config(display_config)
                         .with_clear([0.34, 0.36, 0.52, 1.0]),
                 )
                 .with_plugin(RenderFlat2D::default())
@@ -117,6 +139,11 @@ fn main() -> amethyst::Result<()> {
             resources,
             Loading::default(),
         )?
+        .with_resource(victory)
+        .with_resource(camera)
+        .with_resource(clock)
+        .with_resource(dungeon)
+        .with_resource(settings)
         .build(game_data)?;
     game.run();
 
@@ -0,0 +1,117 @@
+use amethyst::core::math::Point2;
+
+use crate::{
+    fov::{Fog, TileVisibility},
+    level::{Level, LevelTile},
+};
+
+/// Longest side of the minimap on screen, in pixels, however big the level.
+pub const MAX_SIZE: f32 = 192.0;
+
+/// Screen pixels per tile on small levels.
+pub const MAX_SCALE: f32 = 4.0;
+
+pub fn tile_colour(tile: LevelTile) -> [u8; 4] {
+    match tile {
+        LevelTile::Empty => [0, 0, 0, 0],
+        LevelTile::Plain => [150, 120, 80, 255],
+        LevelTile::Grass => [70, 140, 60, 255],
+        LevelTile::Fence => [90, 60, 30, 255],
+        LevelTile::Road => [170, 170, 160, 255],
+        LevelTile::Swamp => [80, 90, 50, 255],
+        LevelTile::Water => [50, 90, 200, 255],
+        LevelTile::Lava => [220, 80, 20, 255],
+        LevelTile::Wall => [60, 60, 70, 255],
+    }
+}
+
+/// Most pixels along a side of the minimap texture. Levels bigger than
+/// this are sampled a tile per pixel, so the texture is no bigger than it
+/// is drawn however big the level.
+pub const MAX_PIXELS: usize = MAX_SIZE as usize;
+
+/// The width and height in pixels of the minimap of a `width` by `height`
+/// level.
+pub fn texture_size(width: usize, height: usize) -> (usize, usize) {
+    let longest = width.max(height);
+    if longest <= MAX_PIXELS {
+        (width, height)
+    } else {
+        (
+            (width * MAX_PIXELS / longest).max(1),
+            (height * MAX_PIXELS / longest).max(1),
+        )
+    }
+}
+
+/// The minimap of `level` as RGBA pixels, `texture_size` of them, top row
+/// first. Each pixel shows the tile under its middle, and tiles the player
+/// hasn't explored are left transparent.
+pub fn render(level: &Level, fog: &Fog) -> Vec<u8> {
+    let (width, height) = texture_size(level.width, level.height);
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for py in (0..height).rev() {
+        for px in 0..width {
+            let x = (2 * px + 1) * level.width / (2 * width);
+            let y = (2 * py + 1) * level.height / (2 * height);
+            let p = Point2::new(x as u32, y as u32);
+            let colour = match (fog.visibility(p), level.get_tile(p)) {
+                (TileVisibility::Unseen, _) | (_, Err(_)) => [0, 0, 0, 0],
+                (_, Ok(tile)) => tile_colour(tile),
+            };
+            pixels.extend_from_slice(&colour);
+        }
+    }
+    pixels
+}
+
+/// Screen pixels per tile on the minimap of a `width` by `height` level.
+pub fn scale(width: usize, height: usize) -> f32 {
+    (MAX_SIZE / width.max(height).max(1) as f32).min(MAX_SCALE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_pixel_per_tile_top_row_first() {
+        let level = Level::from_ascii(1, "Test", "%.\n,#").unwrap();
+        let pixels = render(&level, &Fog::default());
+        assert_eq!(pixels.len(), 4 * 4);
+        assert_eq!(pixels[0..4], tile_colour(LevelTile::Water));
+        assert_eq!(pixels[4..8], tile_colour(LevelTile::Plain));
+        assert_eq!(pixels[8..12], tile_colour(LevelTile::Grass));
+        assert_eq!(pixels[12..16], tile_colour(LevelTile::Wall));
+    }
+
+    #[test]
+    fn unexplored_tiles_are_blank() {
+        let mut level = Level::from_ascii(1, "Test", "..#..").unwrap();
+        level.sight = Some(10);
+        let mut fog = Fog::default();
+        fog.update(&level, Point2::new(0, 0), 0);
+        let pixels = render(&level, &fog);
+        assert_eq!(pixels[8..12], tile_colour(LevelTile::Wall));
+        assert_eq!(pixels[12..20], [0; 8]);
+    }
+
+    #[test]
+    fn big_levels_are_sampled() {
+        assert_eq!(texture_size(100, 50), (100, 50));
+        assert_eq!(texture_size(16384, 4096), (MAX_PIXELS, MAX_PIXELS / 4));
+
+        let mut level = Level::filled(1, "Huge", 16384, 16384, LevelTile::Water);
+        level.set_tile(Point2::new(8192, 8192), LevelTile::Lava);
+        let pixels = render(&level, &Fog::default());
+        assert_eq!(pixels.len(), MAX_PIXELS * MAX_PIXELS * 4);
+        assert_eq!(pixels[0..4], tile_colour(LevelTile::Water));
+    }
+
+    #[test]
+    fn big_levels_shrink_to_fit() {
+        assert_eq!(scale(10, 20), MAX_SCALE);
+        assert_eq!(scale(384, 96), 0.5);
+        assert_eq!(scale(0, 0), MAX_SCALE);
+    }
+}
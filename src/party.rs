@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::ItemList;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartyMember {
+    pub name: String,
+    pub hp: u32,
+    pub max_hp: u32,
+}
+
+impl PartyMember {
+    pub fn new(name: &str, max_hp: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            hp: max_hp,
+            max_hp,
+        }
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.hp == 0
+    }
+
+    pub fn damage(&mut self, amount: u32) {
+        self.hp = self.hp.saturating_sub(amount);
+    }
+}
+
+/// The player's party, kept as a resource so battles and the end-condition
+/// checks can share it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Party {
+    pub members: Vec<PartyMember>,
+    /// Names of the items the party carries, from the item lists.
+    #[serde(default)]
+    pub items: Vec<String>,
+}
+
+impl Default for Party {
+    fn default() -> Self {
+        Self {
+            members: vec![PartyMember::new("Hero", 30)],
+            items: vec!["Torch".to_string()],
+        }
+    }
+}
+
+impl Party {
+    /// The party is defeated once every member is down.
+    pub fn is_defeated(&self) -> bool {
+        self.members.iter().all(PartyMember::is_down)
+    }
+
+    /// The furthest any carried item lights up, 0 if none give light.
+    pub fn light_radius(&self, items: &ItemList) -> u32 {
+        self.items
+            .iter()
+            .filter_map(|name| items.find(name))
+            .filter_map(|item| item.light_radius)
+            .max()
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,329 @@
+use amethyst::core::math::Point3;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet},
+};
+
+use crate::{
+    data::Terrain,
+    grid::{Direction, MovementMode},
+    level::Level,
+};
+
+/// A* search over the open tiles of a `Level`.
+///
+/// Steps follow the same rules as the player: `Level::step` decides which
+/// moves are allowed, and each one costs the terrain's step cost for the
+/// tile stepped onto, times the length of the step.
+pub struct PathFinder<'a> {
+    level: &'a Level,
+    mode: MovementMode,
+    terrain: Option<&'a Terrain>,
+    occupied: HashSet<(u32, u32)>,
+    node_limit: usize,
+}
+
+impl<'a> PathFinder<'a> {
+    pub fn new(level: &'a Level) -> Self {
+        Self {
+            level,
+            mode: level.movement.unwrap_or_default(),
+            terrain: None,
+            occupied: HashSet::new(),
+            node_limit: 100_000,
+        }
+    }
+
+    pub fn with_mode(mut self, mode: MovementMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Weighs steps by the terrain's step costs rather than counting tiles.
+    pub fn with_terrain(mut self, terrain: &'a Terrain) -> Self {
+        self.terrain = Some(terrain);
+        self
+    }
+
+    /// Tiles held by other entities. The goal itself may still be occupied.
+    pub fn with_occupied(mut self, occupied: HashSet<(u32, u32)>) -> Self {
+        self.occupied = occupied;
+        self
+    }
+
+    /// Gives up after expanding this many tiles.
+    pub fn with_node_limit(mut self, node_limit: usize) -> Self {
+        self.node_limit = node_limit;
+        self
+    }
+
+    /// The tiles to step through to get from `start` to `goal`, not
+    /// including `start`, or `None` if the goal can't be reached.
+    pub fn find(&self, start: Point3<u32>, goal: Point3<u32>) -> Option<Vec<Point3<u32>>> {
+        // Where the level wraps, the same tile can go by many coordinates
+        let on_level = |p: Point3<u32>| {
+            let p = self.level.wrap(i64::from(p.x), i64::from(p.y))?;
+            Some(Point3::new(p.x, p.y, 0))
+        };
+        let (start, goal) = (on_level(start)?, on_level(goal)?);
+        if start == goal {
+            return Some(Vec::new());
+        }
+        if self.level.is_blocking(goal.xy()) {
+            return None;
+        }
+
+        let directions: &[Direction] = match self.mode {
+            MovementMode::FourWay => &Direction::CARDINAL,
+            MovementMode::EightWay => &Direction::ALL,
+        };
+        let min_cost = self.min_step_cost();
+
+        // Per-tile search state, indexed by `index`
+        let tiles = self.level.width * self.level.height;
+        let index = |p: Point3<u32>| p.y as usize * self.level.width + p.x as usize;
+        let mut came_from: Vec<Option<Point3<u32>>> = vec![None; tiles];
+        let mut costs = vec![std::f32::INFINITY; tiles];
+
+        let mut open = BinaryHeap::new();
+        costs[index(start)] = 0.0;
+        open.push(Node {
+            estimate: self.heuristic(start, goal) * min_cost,
+            cost: 0.0,
+            point: start,
+        });
+
+        let mut expanded = 0;
+        while let Some(Node { cost, point, .. }) = open.pop() {
+            if point == goal {
+                let mut path = vec![goal];
+                let mut current = goal;
+                while let Some(previous) = came_from[index(current)] {
+                    if previous == start {
+                        break;
+                    }
+                    path.push(previous);
+                    current = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            // Stale entry for a tile already reached more cheaply
+            if cost > costs[index(point)] {
+                continue;
+            }
+            expanded += 1;
+            if expanded > self.node_limit {
+                return None;
+            }
+
+            for &direction in directions {
+                let next = match self.level.step(point, direction) {
+                    Some(next) => next,
+                    None => continue,
+                };
+                if next != goal && self.occupied.contains(&(next.x, next.y)) {
+                    continue;
+                }
+                let next_cost = cost + self.step_cost(next) * direction.distance();
+                if next_cost < costs[index(next)] {
+                    costs[index(next)] = next_cost;
+                    came_from[index(next)] = Some(point);
+                    open.push(Node {
+                        estimate: next_cost + self.heuristic(next, goal) * min_cost,
+                        cost: next_cost,
+                        point: next,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn step_cost(&self, p: Point3<u32>) -> f32 {
+        match (self.terrain, self.level.get_tile(p.xy())) {
+            (Some(terrain), Ok(tile)) => terrain.properties(tile).step_cost,
+            _ => 1.0,
+        }
+    }
+
+    /// The cheapest step on the map, so the heuristic never overestimates.
+    fn min_step_cost(&self) -> f32 {
+        self.terrain
+            .map(|terrain| {
+                terrain
+                    .tiles
+                    .values()
+                    .map(|properties| properties.step_cost)
+                    .fold(1.0, f32::min)
+            })
+            .unwrap_or(1.0)
+            .max(0.0)
+    }
+
+    /// Manhattan distance on four-way levels, octile distance on eight-way,
+    /// the short way round on levels that wrap.
+    fn heuristic(&self, a: Point3<u32>, b: Point3<u32>) -> f32 {
+        let offset = self.level.offset(a.xy(), b.xy());
+        let (dx, dy) = (offset.x.abs() as f32, offset.y.abs() as f32);
+        match self.mode {
+            MovementMode::FourWay => dx + dy,
+            MovementMode::EightWay => dx.max(dy) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dy),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    estimate: f32,
+    cost: f32,
+    point: Point3<u32>,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Node {}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Node {
+    /// Reversed, so the `BinaryHeap` pops the lowest estimate first. Ties go
+    /// to the node furthest along, which keeps the search heading for the
+    /// goal across open ground.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| {
+                self.cost
+                    .partial_cmp(&other.cost)
+                    .unwrap_or(Ordering::Equal)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{data::TileProperties, grid::Topology, level::LevelTile};
+
+    /// A `size` square map with nothing in the way.
+    fn open(size: usize) -> Level {
+        let row = ".".repeat(size);
+        Level::from_ascii(1, "Open", &vec![row; size].join("\n")).unwrap()
+    }
+
+    fn p(x: u32, y: u32) -> Point3<u32> {
+        Point3::new(x, y, 0)
+    }
+
+    fn level(data: &str) -> Level {
+        Level::from_ascii(1, "Test", data).unwrap()
+    }
+
+    #[test]
+    fn straight_line() {
+        let level = level(".....");
+        let path = PathFinder::new(&level).find(p(0, 0), p(4, 0)).unwrap();
+        assert_eq!(path, vec![p(1, 0), p(2, 0), p(3, 0), p(4, 0)]);
+        assert_eq!(PathFinder::new(&level).find(p(2, 0), p(2, 0)), Some(vec![]));
+    }
+
+    #[test]
+    fn around_a_wall() {
+        let level = level("...\n.-.\n.-.");
+        let path = PathFinder::new(&level).find(p(0, 0), p(2, 0)).unwrap();
+        assert_eq!(path.len(), 6);
+        assert_eq!(path[path.len() - 1], p(2, 0));
+        assert!(path.iter().all(|q| !level.is_blocking(q.xy())));
+
+        let path = PathFinder::new(&level)
+            .with_mode(MovementMode::EightWay)
+            .find(p(0, 0), p(2, 0))
+            .unwrap();
+        assert_eq!(path, vec![p(0, 1), p(1, 2), p(2, 1), p(2, 0)]);
+    }
+
+    #[test]
+    fn unreachable() {
+        let level = level("..-..");
+        assert_eq!(PathFinder::new(&level).find(p(0, 0), p(4, 0)), None);
+        assert_eq!(PathFinder::new(&level).find(p(0, 0), p(2, 0)), None);
+        assert_eq!(PathFinder::new(&level).find(p(0, 0), p(9, 0)), None);
+    }
+
+    #[test]
+    fn wraps_around_the_world() {
+        let mut level = level("..-..\n..-..");
+        assert_eq!(PathFinder::new(&level).find(p(4, 0), p(1, 0)), None);
+
+        level.topology = Topology::WrapHorizontal;
+        let path = PathFinder::new(&level).find(p(4, 0), p(1, 0));
+        assert_eq!(path, Some(vec![p(0, 0), p(1, 0)]));
+        // Coordinates past the edge are the same tiles
+        let path = PathFinder::new(&level).find(p(9, 0), p(6, 0));
+        assert_eq!(path, Some(vec![p(0, 0), p(1, 0)]));
+    }
+
+    #[test]
+    fn avoids_occupied_tiles() {
+        let level = level("...\n...");
+        let occupied = vec![(1, 0)].into_iter().collect();
+        let path = PathFinder::new(&level)
+            .with_occupied(occupied)
+            .find(p(0, 0), p(2, 0))
+            .unwrap();
+        assert_eq!(path, vec![p(0, 1), p(1, 1), p(2, 1), p(2, 0)]);
+
+        // Walking up to an occupied goal is allowed
+        let occupied = vec![(2, 0)].into_iter().collect();
+        let path = PathFinder::new(&level)
+            .with_occupied(occupied)
+            .find(p(0, 0), p(2, 0));
+        assert_eq!(path, Some(vec![p(1, 0), p(2, 0)]));
+    }
+
+    #[test]
+    fn prefers_cheap_terrain() {
+        let level = level("=====\n~~~~~");
+        let mut terrain = Terrain::default();
+        terrain.tiles.insert(
+            LevelTile::Road,
+            TileProperties {
+                step_cost: 0.5,
+                ..TileProperties::default()
+            },
+        );
+        terrain.tiles.insert(
+            LevelTile::Swamp,
+            TileProperties {
+                step_cost: 3.0,
+                ..TileProperties::default()
+            },
+        );
+        let path = PathFinder::new(&level)
+            .with_terrain(&terrain)
+            .find(p(0, 0), p(4, 0))
+            .unwrap();
+        assert_eq!(
+            path,
+            vec![p(0, 1), p(1, 1), p(2, 1), p(3, 1), p(4, 1), p(4, 0)]
+        );
+    }
+
+    #[test]
+    fn node_limit() {
+        let level = open(50);
+        let finder = PathFinder::new(&level).with_node_limit(10);
+        assert_eq!(finder.find(p(0, 0), p(49, 49)), None);
+    }
+}
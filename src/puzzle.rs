@@ -0,0 +1,72 @@
+use amethyst::core::math::Point3;
+use std::collections::HashSet;
+
+use crate::{component::Position, grid::Direction, level::Level};
+
+/// Where the player entered the current room, to be put back on a reset.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoomEntry(pub Option<Position>);
+
+/// Where a block at `block` ends up when pushed in `direction`, or `None`
+/// if it can't move. Blocks only go straight, onto an open tile nobody else
+/// is standing on or heading to.
+pub fn push_target(
+    level: &Level,
+    occupied: &HashSet<(u32, u32)>,
+    block: Point3<u32>,
+    direction: Direction,
+) -> Option<Point3<u32>> {
+    if direction.is_diagonal() {
+        return None;
+    }
+    let target = level.step(block, direction)?;
+    if occupied.contains(&(target.x, target.y)) {
+        None
+    } else {
+        Some(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(x: u32, y: u32) -> Point3<u32> {
+        Point3::new(x, y, 0)
+    }
+
+    #[test]
+    fn pushes_onto_open_tiles() {
+        let level = Level::from_ascii(1, "Test", "....\n...-").unwrap();
+        let none = HashSet::new();
+        assert_eq!(
+            push_target(&level, &none, p(1, 0), Direction::East),
+            Some(p(2, 0))
+        );
+        assert_eq!(
+            push_target(&level, &none, p(1, 0), Direction::North),
+            Some(p(1, 1))
+        );
+        // Into the fence, off the map, or diagonally
+        assert_eq!(push_target(&level, &none, p(2, 0), Direction::East), None);
+        assert_eq!(push_target(&level, &none, p(1, 0), Direction::South), None);
+        assert_eq!(
+            push_target(&level, &none, p(1, 0), Direction::NorthEast),
+            None
+        );
+    }
+
+    #[test]
+    fn blocks_do_not_stack() {
+        let level = Level::from_ascii(1, "Test", "....").unwrap();
+        let occupied = vec![(2, 0)].into_iter().collect();
+        assert_eq!(
+            push_target(&level, &occupied, p(1, 0), Direction::East),
+            None
+        );
+        assert_eq!(
+            push_target(&level, &occupied, p(1, 0), Direction::West),
+            Some(p(0, 0))
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use amethyst::{config::Config, utils::application_root_dir, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use crate::{
+    chunk::TileSet,
+    clock::WorldClock,
+    dungeon::{DungeonFloor, OpenedChests},
+    party::Party,
+    story::Flags,
+};
+
+/// Everything needed to put the player back where they were.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub level: i32,
+    pub position: (u32, u32),
+    pub party: Party,
+    pub flags: Flags,
+    /// Tiles seen so far on each level, as kept by `Fog`.
+    #[serde(default, deserialize_with = "explored_or_old")]
+    pub explored: HashMap<i32, TileSet>,
+    #[serde(default)]
+    pub clock: Option<WorldClock>,
+    /// The dungeon floor `level` is, if it isn't one of the manifest's.
+    #[serde(default)]
+    pub dungeon: Option<DungeonFloor>,
+    #[serde(default)]
+    pub opened_chests: OpenedChests,
+}
+
+impl SaveGame {
+    /// Reads the most recent save, if there is one.
+    pub fn load_last() -> Option<Self> {
+        let path = save_path();
+        if !path.exists() {
+            return None;
+        }
+        match <Self as Config>::load(&path) {
+            Ok(save) => Some(save),
+            Err(e) => {
+                log::error!("Failed to read save {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn write_last(&self) -> Result<()> {
+        let path = save_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        self.write(&path)?;
+        log::info!("Saved game to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Saves from before `Fog` kept to the chunks the player had looked into
+/// listed every tile of each level, which can't be placed again without the
+/// level's width, so those levels start out unexplored.
+fn explored_or_old<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<HashMap<i32, TileSet>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Explored {
+        Chunks(TileSet),
+        Tiles(Vec<bool>),
+    }
+
+    let levels = HashMap::<i32, Explored>::deserialize(deserializer)?;
+    Ok(levels
+        .into_iter()
+        .filter_map(|(level, explored)| match explored {
+            Explored::Chunks(tiles) => Some((level, tiles)),
+            Explored::Tiles(_) => None,
+        })
+        .collect())
+}
+
+fn save_path() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join("dd"))
+        .unwrap_or_else(|| application_root_dir().expect("root dir").join("saves"))
+        .join("save.ron")
+}
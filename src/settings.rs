@@ -0,0 +1,228 @@
+use amethyst::{
+    audio::AudioSink,
+    config::Config,
+    input::{Bindings, StringBindings},
+    utils::application_root_dir,
+    window::{DisplayConfig, Window},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+use winit::dpi::LogicalSize;
+
+use crate::{bindings, grid::MovementMode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TextSpeed {
+    Slow,
+    Normal,
+    Fast,
+    Instant,
+}
+
+impl TextSpeed {
+    /// Characters revealed per second, `None` meaning the whole text at once.
+    pub fn chars_per_second(self) -> Option<f32> {
+        match self {
+            TextSpeed::Slow => Some(20.0),
+            TextSpeed::Normal => Some(40.0),
+            TextSpeed::Fast => Some(80.0),
+            TextSpeed::Instant => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WindowMode {
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+/// The player's preferences, stored in the platform config directory and
+/// layered over `display_config.ron` and `input_bindings.ron` at startup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub volume: f32,
+    pub text_speed: TextSpeed,
+    pub window_mode: WindowMode,
+    pub dimensions: Option<(u32, u32)>,
+    /// Multiplier on the time it takes to walk one tile.
+    pub movement_speed: f32,
+    /// Used on levels that don't set their own.
+    pub movement_mode: MovementMode,
+    /// How far the analog stick must be pushed before the player walks.
+    pub stick_deadzone: f32,
+    pub bindings: Option<Bindings<StringBindings>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            text_speed: TextSpeed::Normal,
+            window_mode: WindowMode::Windowed,
+            dimensions: None,
+            movement_speed: 1.0,
+            movement_mode: MovementMode::FourWay,
+            stick_deadzone: 0.3,
+            bindings: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the user's settings, falling back to the defaults if there are
+    /// none yet or they can't be read.
+    pub fn load_user() -> Self {
+        let path = settings_path();
+        if !path.exists() {
+            return Self::default();
+        }
+        match <Self as Config>::load(&path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::error!("Failed to read settings {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn write_user(&self) -> Result<()> {
+        let path = settings_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        self.write(&path)?;
+        log::info!("Saved settings to {}", path.display());
+        Ok(())
+    }
+
+    /// Overrides the parts of `display_config.ron` the user can change.
+    pub fn apply_display_config(&self, config: &mut DisplayConfig) {
+        if let Some(dimensions) = self.dimensions {
+            config.dimensions = Some(dimensions);
+        }
+        config.decorations = self.window_mode == WindowMode::Windowed;
+        config.maximized = self.window_mode == WindowMode::Borderless;
+    }
+
+    /// Applies the window mode to an already open window.
+    pub fn apply_window(&self, window: &Window) {
+        match self.window_mode {
+            WindowMode::Windowed => {
+                window.set_fullscreen(None);
+                window.set_decorations(true);
+                window.set_maximized(false);
+                if let Some((width, height)) = self.dimensions {
+                    window.set_inner_size(LogicalSize::new(width.into(), height.into()));
+                }
+            }
+            WindowMode::Borderless => {
+                window.set_fullscreen(None);
+                window.set_decorations(false);
+                window.set_maximized(true);
+            }
+            WindowMode::Fullscreen => {
+                window.set_fullscreen(Some(window.get_current_monitor()));
+            }
+        }
+    }
+
+    /// Sets the volume everything the game plays through its audio sink is
+    /// heard at.
+    pub fn apply_volume(&self, sink: &mut AudioSink) {
+        sink.set_volume(self.volume);
+    }
+
+    /// The key bindings to play with: `defaults` from `input_bindings.ron`
+    /// with any the user has saved over them.
+    pub fn bindings_over(&self, defaults: Bindings<StringBindings>) -> Bindings<StringBindings> {
+        match &self.bindings {
+            Some(saved) => bindings::overlay(&defaults, saved),
+            None => defaults,
+        }
+    }
+
+    /// Time to walk one tile at the user's movement speed.
+    pub fn step_secs(&self, base: f32) -> f32 {
+        base / self.movement_speed.max(0.1)
+    }
+}
+
+fn settings_path() -> PathBuf {
+    dirs::config_dir()
+        .map(|dir| dir.join("dd"))
+        .unwrap_or_else(|| application_root_dir().expect("root dir").join("config"))
+        .join("settings.ron")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::input::{Axis, Button, VirtualKeyCode};
+
+    fn key(key: VirtualKeyCode) -> Button {
+        Button::Key(key)
+    }
+
+    /// Settings saved before the minimap had a binding.
+    const OLD_SETTINGS: &str = r#"(
+    volume: 0.5,
+    bindings: Some((
+        axes: {
+            "east_west": Emulated(pos: Key(Right), neg: Key(Left)),
+        },
+        actions: {
+            "confirm": [[Key(Z)]],
+        },
+    )),
+)"#;
+
+    #[test]
+    fn saved_bindings_keep_newer_defaults() {
+        let path = std::env::temp_dir().join("dd-old-settings.ron");
+        fs::write(&path, OLD_SETTINGS).unwrap();
+        let settings = <Settings as Config>::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut defaults = Bindings::<StringBindings>::new();
+        defaults
+            .insert_axis(
+                "east_west",
+                Axis::Emulated {
+                    pos: key(VirtualKeyCode::D),
+                    neg: key(VirtualKeyCode::A),
+                },
+            )
+            .unwrap();
+        defaults
+            .insert_action_binding("confirm".to_string(), vec![key(VirtualKeyCode::Return)])
+            .unwrap();
+        defaults
+            .insert_action_binding("minimap".to_string(), vec![key(VirtualKeyCode::M)])
+            .unwrap();
+        // Taken by the saved confirm binding
+        defaults
+            .insert_action_binding("minimap".to_string(), vec![key(VirtualKeyCode::Z)])
+            .unwrap();
+
+        let bindings = settings.bindings_over(defaults);
+        let combos = |name: &str| {
+            bindings
+                .action_bindings(name)
+                .map(|combo| combo.to_vec())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(combos("confirm"), vec![vec![key(VirtualKeyCode::Z)]]);
+        assert_eq!(combos("minimap"), vec![vec![key(VirtualKeyCode::M)]]);
+        assert_eq!(
+            bindings.axis("east_west"),
+            Some(&Axis::Emulated {
+                pos: key(VirtualKeyCode::Right),
+                neg: key(VirtualKeyCode::Left),
+            })
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use amethyst::{
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
+    prelude::*,
+    utils::application_root_dir,
+};
+use std::fs;
+
+use super::{
+    menu::{Menu, MenuResult},
+    title::TitleState,
+    RuntimeSystemState,
+};
+use crate::events::GameStateEvent;
+
+/// Rolls the contents of `resources/credits.txt` after the game is won.
+#[derive(Default)]
+pub struct CreditsState {
+    menu: Option<Menu<()>>,
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, GameStateEvent> for CreditsState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+        *data.world.write_resource() = RuntimeSystemState::Paused;
+
+        let mut menu = Menu::new("Credits");
+        let path = application_root_dir()
+            .expect("root dir")
+            .join("resources")
+            .join("credits.txt");
+        match fs::read_to_string(&path) {
+            Ok(credits) => {
+                for line in credits.lines() {
+                    menu = menu.with_text(line);
+                }
+            }
+            Err(e) => log::error!("Failed to read {}: {}", path.display(), e),
+        }
+        let mut menu = menu.with_item("Return to title", ());
+        menu.register(data.world);
+        self.menu = Some(menu);
+    }
+
+    fn on_resume(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+        self.menu
+            .as_mut()
+            .expect("menu built on start")
+            .resume(data.world);
+    }
+
+    fn handle_event(
+        &mut self,
+        _: StateData<'_, GameData<'a, 'b>>,
+        event: GameStateEvent,
+    ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
+        if let GameStateEvent::Window(event) = event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Switch(Box::new(TitleState::default()));
+            }
+        }
+        Trans::None
+    }
+
+    fn update(
+        &mut self,
+        data: StateData<'_, GameData<'a, 'b>>,
+    ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
+        data.data.update(&data.world);
+
+        let menu = self.menu.as_mut().expect("menu built on start");
+        match menu.update(data.world) {
+            Some(MenuResult::Chosen(())) | Some(MenuResult::Cancelled) => {
+                Trans::Switch(Box::new(TitleState::default()))
+            }
+            None => Trans::None,
+        }
+    }
+}
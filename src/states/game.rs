@@ -1,32 +1,88 @@
 use amethyst::{
+    assets::{AssetStorage, Loader},
     core::{
-        math::{Point3, Vector3},
+        math::{Point2, Point3, Vector3},
         transform::Transform,
         Parent,
     },
-    ecs::{Entity, World},
+    ecs::{Entity, Join, World},
     input::{is_close_requested, is_key_down, VirtualKeyCode},
     prelude::*,
-    renderer::{sprite::SpriteSheetHandle, Camera, SpriteRender},
-    tiles::{Map, MortonEncoder2D, Tile},
-    utils::{
-        application_root_dir,
-        ortho_camera::{CameraNormalizeMode, CameraOrtho, CameraOrthoWorldCoordinates},
+    renderer::{
+        palette::Srgba, sprite::SpriteSheetHandle, Camera, SpriteRender, SpriteSheet, Texture,
     },
+    tiles::{MortonEncoder2D, Tile},
+    utils::ortho_camera::{CameraNormalizeMode, CameraOrtho, CameraOrthoWorldCoordinates},
     window::ScreenDimensions,
 };
 
+use super::{credits::CreditsState, game_over::GameOverState};
 use crate::{
-    component::{Player, Position},
-    events::GameStateEvent,
-    level::{Level, LevelTile},
+    assets::GameAssets,
+    autotile::Autotiles,
+    camera::CameraScript,
+    chunk::{tile_in_chunk, MapGrid},
+    clock::{ClockConfig, WorldClock},
+    component::{
+        CharacterSprite, Chest, Facing, FromMarker, Npc, Player, Position, PressurePlate, Pushable,
+        Stairs,
+    },
+    data::{ItemList, MonsterList, Terrain},
+    dungeon::{self, CurrentFloor, DungeonConfig, DungeonFloor, OpenedChests},
+    events::{GameEvent, GameStateEvent},
+    fov::{Fog, TileVisibility},
+    level::{Level, MarkerKind},
+    lighting::Lighting,
+    party::Party,
+    puzzle::RoomEntry,
+    rng::Rng,
+    save::SaveGame,
     states::RuntimeSystemState,
+    story::{Flags, VictoryCondition},
+    system::tiles::TileFrames,
+    tileset::TileLayers,
 };
 
 pub type TileMap = amethyst::tiles::TileMap<GameTile, MortonEncoder2D>;
 
+/// Name of the manifest sprite sheet used to draw the tilemap.
+pub const TERRAIN_SHEET: &str = "terrain";
+
+/// Name of the manifest sprite sheet with the player and NPC walk cycles.
+pub const CHARACTER_SHEET: &str = "character";
+
+/// Frames of the terrain sheet for the things placed on dungeon floors.
+const STAIRS_DOWN_SPRITE: usize = 41;
+const STAIRS_UP_SPRITE: usize = 42;
+const CHEST_SPRITE: usize = 43;
+
+/// Counts the games started since launch, so systems that remember
+/// something from frame to frame can tell they're in a new one and forget
+/// it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GameSession(pub u32);
+
 pub struct GameState {
     pub sheet_handle: SpriteSheetHandle,
+    pub character_sheet: SpriteSheetHandle,
+    /// Save to resume from, or `None` to start a new game.
+    pub save: Option<SaveGame>,
+}
+
+impl GameState {
+    pub fn new(world: &World, save: Option<SaveGame>) -> Self {
+        let sheet_handle = fetch_sprite_sheet(world, TERRAIN_SHEET)
+            .expect("Expected the terrain sheet to be listed in the manifest");
+        let assets = world.read_resource::<GameAssets>();
+        let character_sheet = assets
+            .sprite_sheet(CHARACTER_SHEET)
+            .expect("Expected the character sheet to be listed in the manifest");
+        Self {
+            sheet_handle,
+            character_sheet,
+            save,
+        }
+    }
 }
 
 impl<'a, 'b> State<GameData<'a, 'b>, GameStateEvent> for GameState {
@@ -36,32 +92,89 @@ impl<'a, 'b> State<GameData<'a, 'b>, GameStateEvent> for GameState {
     fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
         let world = data.world;
         *world.write_resource() = RuntimeSystemState::Running;
+        world
+            .entry::<GameSession>()
+            .or_insert_with(GameSession::default)
+            .0 += 1;
 
         // Get the screen dimensions so we can initialize the camera and
         // place our sprites correctly later. We'll clone this since we'll
         // pass the world mutably to the following functions.
         let dimensions = (*world.read_resource::<ScreenDimensions>()).clone();
 
+        // Restore the party and story progress, or start fresh
+        let save = self.save.take();
+        let (level, floor, start) = match save {
+            Some(save) => {
+                world.insert(save.party);
+                world.insert(save.flags);
+                world.insert(save.opened_chests);
+                world.insert(Fog {
+                    explored: save.explored,
+                    ..Fog::default()
+                });
+                let clock = save.clock.unwrap_or_else(|| start_clock(world));
+                world.insert(clock);
+                (
+                    Some(save.level),
+                    save.dungeon,
+                    Point3::new(save.position.0, save.position.1, 0),
+                )
+            }
+            None => {
+                world.insert(Party::default());
+                world.insert(Flags::default());
+                world.insert(OpenedChests::default());
+                world.insert(Fog::default());
+                let clock = start_clock(world);
+                world.insert(clock);
+                (None, None, Point3::new(1, 5, 0))
+            }
+        };
+
         // Load the level
-        init_level(world);
+        init_level(world, level, floor);
+        init_terrain(world);
+        init_items(world);
+        init_monsters(world);
+        world.insert(Rng::default());
+        world.insert(Lighting::default());
+        let autotiles = Autotiles::new(
+            &world.read_resource::<Level>(),
+            &world.read_resource::<Terrain>(),
+        );
+        world.insert(autotiles);
 
         // Load our sprites and display them
-        let (map, map_transform, map_entity) = init_map(world, self.sheet_handle.clone());
-        let player = init_player(
+        let (grid, map_entity) = init_map(world);
+        let player = init_player(world, &grid, &self.character_sheet, map_entity, start);
+        init_markers(
             world,
-            &map,
-            &map_transform,
-            &self.sheet_handle.clone(),
+            &grid,
+            &self.sheet_handle,
+            &self.character_sheet,
             map_entity,
         );
+        world.insert(RoomEntry(Some(Position(start))));
+
+        // Place the camera over the player, from where `CameraSystem` takes
+        // over
+        let player_translation = *world
+            .read_storage::<Transform>()
+            .get(player)
+            .expect("Player should have a transform")
+            .translation();
+        init_camera(world, player_translation, &dimensions);
+        world.insert(CameraScript::default());
+    }
 
-        // Place the camera
-        init_camera(world, player, &dimensions);
+    fn on_stop(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+        data.world.delete_all();
     }
 
     fn handle_event(
         &mut self,
-        _: StateData<'_, GameData<'a, 'b>>,
+        data: StateData<'_, GameData<'a, 'b>>,
         event: GameStateEvent,
     ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
         match event {
@@ -71,6 +184,12 @@ impl<'a, 'b> State<GameData<'a, 'b>, GameStateEvent> for GameState {
                     return Trans::Quit;
                 }
 
+                if is_key_down(&event, VirtualKeyCode::F5) {
+                    if let Some(save) = current_save(data.world) {
+                        autosave(data.world, &save);
+                    }
+                }
+
                 // Listen to any key events
                 // if let Some(event) = get_key(&event) {
                 //     log::info!("handling key event: {:?}", event);
@@ -81,6 +200,36 @@ impl<'a, 'b> State<GameData<'a, 'b>, GameStateEvent> for GameState {
                 // https://book.amethyst.rs/stable/pong-tutorial/pong-tutorial-03.html#capturing-user-input
             }
             GameStateEvent::Ui(_) => {}
+            GameStateEvent::App(GameEvent::GameOver) => {
+                return Trans::Switch(Box::new(GameOverState::default()));
+            }
+            GameStateEvent::App(GameEvent::Victory) => {
+                return Trans::Switch(Box::new(CreditsState::default()));
+            }
+            GameStateEvent::App(GameEvent::Descend) => {
+                if let Some(save) = descend(data.world) {
+                    autosave(data.world, &save);
+                    let next = GameState::new(data.world, Some(save));
+                    return Trans::Switch(Box::new(next));
+                }
+            }
+            GameStateEvent::App(GameEvent::LevelReloaded) => {
+                let grid = data.world.read_resource::<MapGrid>().clone();
+                let root = data.world.read_resource::<TerrainMap>().root;
+                init_markers(
+                    data.world,
+                    &grid,
+                    &self.sheet_handle,
+                    &self.character_sheet,
+                    root,
+                );
+            }
+            GameStateEvent::App(GameEvent::Battle(monster)) => {
+                match data.world.read_resource::<MonsterList>().find(&monster) {
+                    Some(stats) => log::info!("A {} attacks! ({} HP)", monster, stats.hp),
+                    None => log::warn!("A {} attacks, but isn't in the monster lists", monster),
+                }
+            }
             GameStateEvent::App(_) => {}
         }
 
@@ -107,11 +256,10 @@ impl<'a, 'b> State<GameData<'a, 'b>, GameStateEvent> for GameState {
     }
 }
 
-fn init_camera(world: &mut World, player: Entity, dimensions: &ScreenDimensions) {
-    // Center the camera in the middle of the screen, and let it cover
-    // the entire screen
+fn init_camera(world: &mut World, start: Vector3<f32>, dimensions: &ScreenDimensions) {
+    // Let the camera cover the entire screen
     let mut transform = Transform::default();
-    transform.set_translation_xyz(0.0, 0.0, 1.);
+    transform.set_translation_xyz(start.x, start.y, 1.);
 
     let divisor = 2.4;
 
@@ -131,92 +279,414 @@ fn init_camera(world: &mut World, player: Entity, dimensions: &ScreenDimensions)
             },
         ))
         .with(transform)
-        .with(Parent { entity: player })
         .named("camera")
         .build();
 }
 
+/// A tile on one chunk of one of the level's tilemap layers, drawn only if
+/// its kind of tile is drawn from that layer's tileset.
 #[derive(Default, Clone)]
-pub struct GameTile;
+pub struct GameTile {
+    layer: usize,
+    /// Past the edges of levels that wrap, chunks repeat the other side.
+    chunk: (i32, i32),
+}
+
+impl GameTile {
+    pub fn new(layer: usize, chunk: (i32, i32)) -> Self {
+        Self { layer, chunk }
+    }
+
+    /// Where tile `p` of this tile's chunk is on the level.
+    fn on_level(&self, p: Point3<u32>, level: &Level) -> Option<Point2<u32>> {
+        tile_in_chunk(level, self.chunk, p.xy())
+    }
+}
+
 impl Tile for GameTile {
     fn sprite(&self, p: Point3<u32>, w: &World) -> Option<usize> {
-        let level = w.try_fetch::<Level>();
-        if level.is_none() {
-            return None;
+        let level = w.try_fetch::<Level>()?;
+        let in_chunk = p.xy();
+        let p = self.on_level(p, &level)?;
+        let tile = level.get_tile(p).ok()?;
+        if let Some(layers) = w.try_fetch::<TileLayers>() {
+            if layers.layer(tile) != self.layer {
+                return None;
+            }
+        }
+        // Nothing is drawn where the player has never looked
+        if let Some(fog) = w.try_fetch::<Fog>() {
+            if fog.visibility(p) == TileVisibility::Unseen {
+                return None;
+            }
         }
-        let level = level.unwrap();
-        if level.in_bounds(p.xy()) {
-            match level.get_tile(p.xy()).expect("Hopefully we don't crash") {
-                LevelTile::Plain => Some(0),
-                LevelTile::Grass => Some(1),
-                LevelTile::Fence => None,
-                LevelTile::Empty => None,
-            }
-        } else {
-            None
+        // Fences and walls that join up with their neighbours
+        let autotiled = w
+            .try_fetch::<Autotiles>()
+            .and_then(|autotiles| autotiles.sprite(self.chunk, in_chunk));
+        if autotiled.is_some() {
+            return autotiled;
         }
+        let frames = w.try_fetch::<TileFrames>()?;
+        frames.sprite(tile)
     }
+
+    /// Lit by the time of day and nearby lights, and dimmed further where
+    /// the player remembers the tile but can't see it right now.
+    fn tint(&self, p: Point3<u32>, w: &World) -> Srgba {
+        let p = match w.try_fetch::<Level>() {
+            Some(level) => self.on_level(p, &level),
+            None => None,
+        };
+        // Not drawn at all off the level
+        let p = match p {
+            Some(p) => p,
+            None => return Srgba::new(1.0, 1.0, 1.0, 1.0),
+        };
+        let (r, g, b) = w
+            .try_fetch::<Lighting>()
+            .map(|lighting| lighting.light_at(Point2::new(p.x as f32, p.y as f32)))
+            .unwrap_or((1.0, 1.0, 1.0));
+        match w.try_fetch::<Fog>() {
+            Some(ref fog) if fog.visibility(p) == TileVisibility::Remembered => {
+                Srgba::new(r * 0.4, g * 0.4, b * 0.5, 1.0)
+            }
+            _ => Srgba::new(r, g, b, 1.0),
+        }
+    }
+}
+
+/// The tilesets the level is drawn with and the entity everything on the
+/// map is parented to, for `ChunkStreamingSystem` to build the chunks
+/// around the camera from.
+pub struct TerrainMap {
+    pub root: Entity,
+    /// Each layer's sprite sheet, in `TileLayers` order.
+    pub sheets: Vec<Option<SpriteSheetHandle>>,
 }
 
-fn init_map(world: &mut World, sprites: SpriteSheetHandle) -> (TileMap, Transform, Entity) {
-    let (width, height) = {
+/// Works out the level's tilemap layers, fetching any tilesets that haven't
+/// been loaded yet, and makes the entity the map is drawn under. The
+/// tilemaps themselves are built a chunk at a time as the camera nears
+/// them.
+fn init_map(world: &mut World) -> (MapGrid, Entity) {
+    let layers = TileLayers::build(
+        &world.read_resource::<Level>(),
+        &world.read_resource::<Terrain>(),
+    );
+    let (width, height, topology) = {
         let level = world
             .try_fetch::<Level>()
             .expect("Should have a level by now");
-        (level.width, level.height)
+        (level.width as u32, level.height as u32, level.topology)
     };
-    let level_size = Vector3::new(width as u32, height as u32, 1);
-    let tile_size = Vector3::new(32, 32, 1);
-    let map = TileMap::new(level_size, tile_size, Some(sprites));
-    let transform = Transform::default();
+    let grid = MapGrid::new(Vector3::new(width, height, 1), Vector3::new(32, 32, 1))
+        .with_topology(topology);
 
-    let map_entity = world
+    let sheets = layer_sheets(
+        &layers,
+        &mut world.write_resource::<GameAssets>(),
+        &world.read_resource::<Loader>(),
+        &world.read_resource::<AssetStorage<Texture>>(),
+        &world.read_resource::<AssetStorage<SpriteSheet>>(),
+    );
+    let root = world
         .create_entity()
-        .with(map.clone())
-        .with(transform.clone())
+        .with(Transform::default())
+        .named("map")
         .build();
-    (map, transform, map_entity)
+    world.insert(layers);
+    world.insert(grid.clone());
+    world.insert(TerrainMap { root, sheets });
+    (grid, root)
 }
 
-fn init_level(world: &mut World) {
-    let level: Level = Level::from_file(
-        application_root_dir()
-            .expect("root dir")
-            .join("resources")
-            .join("levels")
-            .join("levels.yaml"),
+/// Each layer's sprite sheet, loading any tilesets that haven't been needed
+/// yet.
+pub fn layer_sheets(
+    layers: &TileLayers,
+    assets: &mut GameAssets,
+    loader: &Loader,
+    textures: &AssetStorage<Texture>,
+    sheets: &AssetStorage<SpriteSheet>,
+) -> Vec<Option<SpriteSheetHandle>> {
+    layers
+        .sheets
+        .iter()
+        .map(|name| {
+            let sprites = assets.fetch_sprite_sheet(name, loader, textures, sheets);
+            if sprites.is_none() {
+                log::error!("Tileset `{}` is missing from the manifest", name);
+            }
+            sprites
+        })
+        .collect()
+}
+
+/// The sprite sheet called `name`, loading it if it's a tileset that hasn't
+/// been needed yet.
+fn fetch_sprite_sheet(world: &World, name: &str) -> Option<SpriteSheetHandle> {
+    world.write_resource::<GameAssets>().fetch_sprite_sheet(
+        name,
+        &world.read_resource::<Loader>(),
+        &world.read_resource::<AssetStorage<Texture>>(),
+        &world.read_resource::<AssetStorage<SpriteSheet>>(),
     )
-    .expect("Error loading level");
+}
+
+/// Inserts the level numbered `number`, or the first level in the manifest.
+/// Dungeon floors aren't in the manifest, but are grown again from their
+/// seed.
+fn init_level(world: &mut World, number: Option<i32>, floor: Option<DungeonFloor>) {
+    world.insert(CurrentFloor(floor));
+    if let Some(floor) = floor {
+        let level = dungeon::generate(&world.read_resource::<DungeonConfig>(), floor);
+        world.insert(level);
+        return;
+    }
+    let level = {
+        let assets = world.read_resource::<GameAssets>();
+        let storage = world.read_resource::<AssetStorage<Level>>();
+        let mut levels = assets.levels.iter().filter_map(|handle| storage.get(handle));
+        match number {
+            Some(number) => levels.find(|level| level.level == number),
+            None => levels.next(),
+        }
+        .expect("Level should be loaded by now")
+        .clone()
+    };
     world.insert(level);
 }
 
+fn init_terrain(world: &mut World) {
+    let terrain = {
+        let assets = world.read_resource::<GameAssets>();
+        let storage = world.read_resource::<AssetStorage<Terrain>>();
+        assets
+            .terrain
+            .as_ref()
+            .and_then(|handle| storage.get(handle).cloned())
+            .unwrap_or_default()
+    };
+    world.insert(terrain);
+}
+
+/// A new game's clock, at the configured starting hour of the first day.
+fn start_clock(world: &World) -> WorldClock {
+    WorldClock::at_hour(world.read_resource::<ClockConfig>().start_hour)
+}
+
+/// Merges every item list in the manifest into one `ItemList` resource.
+fn init_items(world: &mut World) {
+    let items = {
+        let assets = world.read_resource::<GameAssets>();
+        let storage = world.read_resource::<AssetStorage<ItemList>>();
+        ItemList::merged(assets.items.iter().filter_map(|handle| storage.get(handle)))
+    };
+    world.insert(items);
+}
+
+/// Merges every monster list in the manifest into one `MonsterList`
+/// resource.
+fn init_monsters(world: &mut World) {
+    let monsters = {
+        let assets = world.read_resource::<GameAssets>();
+        let storage = world.read_resource::<AssetStorage<MonsterList>>();
+        MonsterList::merged(
+            assets
+                .monsters
+                .iter()
+                .filter_map(|handle| storage.get(handle)),
+        )
+    };
+    world.insert(monsters);
+}
+
 fn init_player(
     world: &mut World,
-    map: &TileMap,
-    map_transform: &Transform,
+    grid: &MapGrid,
     sprite_sheet: &SpriteSheetHandle,
     map_entity: Entity,
+    start: Point3<u32>,
 ) -> Entity {
-    log::info!("{:?}", map_transform);
-    let pos = Position(Point3::new(1, 5, 0));
-    let mut transform = Transform::from(map.to_world(&Point3::new(1, 5, 0), None));
+    let pos = Position(start);
+    let mut transform = Transform::from(grid.to_world(&start));
     transform.translation_mut().z += 0.1;
-    transform.scale_mut().x *= 0.7;
-    transform.scale_mut().y *= 0.7;
     log::info!("{:?}", transform);
-    let sprite = SpriteRender {
-        sprite_sheet: sprite_sheet.clone(),
-        sprite_number: 1,
-    };
 
     let player = world
         .create_entity()
         .with(transform)
         .with(Player)
-        .with(sprite)
+        .with(sprite(sprite_sheet, 0))
+        .with(Facing::default())
+        .with(CharacterSprite::default())
         .with(pos)
         .with(Parent { entity: map_entity })
         .named("player")
         .build();
     player
 }
+
+/// Spawns the NPCs, blocks, pressure plates, lights, stairs and chests
+/// placed in the level's markers.
+fn init_markers(
+    world: &mut World,
+    grid: &MapGrid,
+    sprite_sheet: &SpriteSheetHandle,
+    character_sheet: &SpriteSheetHandle,
+    map_entity: Entity,
+) {
+    let (number, markers) = {
+        let level = world.read_resource::<Level>();
+        (level.level, level.markers.clone())
+    };
+    for marker in markers {
+        // Nothing to spawn, `TerrainSystem` checks the level's zones itself
+        if let MarkerKind::EncounterZone { .. } = marker.kind {
+            continue;
+        }
+        // Opened for good
+        if let MarkerKind::Chest { .. } = marker.kind {
+            if world
+                .read_resource::<OpenedChests>()
+                .is_opened(number, marker.position)
+            {
+                continue;
+            }
+        }
+        let at = Position(Point3::new(marker.position.0, marker.position.1, 0));
+        let mut transform = Transform::from(grid.to_world(&at.0));
+        transform.translation_mut().z += 0.1;
+        let builder = world
+            .create_entity()
+            .with(Parent { entity: map_entity })
+            .with(FromMarker);
+        // Blocks and plates borrow the terrain art until they have their own
+        match marker.kind {
+            MarkerKind::Npc {
+                name,
+                goal,
+                schedule,
+            } => {
+                builder
+                    .with(transform)
+                    .with(sprite(character_sheet, 0))
+                    .with(Facing::default())
+                    .with(CharacterSprite::default())
+                    .with(at)
+                    .with(Npc {
+                        name: name.clone(),
+                        goal,
+                        schedule,
+                    })
+                    .named(name)
+                    .build();
+            }
+            MarkerKind::Block => {
+                transform.scale_mut().x *= 0.9;
+                transform.scale_mut().y *= 0.9;
+                builder
+                    .with(transform)
+                    .with(sprite(sprite_sheet, 0))
+                    .with(at)
+                    .with(Pushable { home: at })
+                    .build();
+            }
+            MarkerKind::PressurePlate { flag, latch } => {
+                // Below anything standing on it, and not a `Position` so it
+                // doesn't get in anyone's way
+                transform.translation_mut().z -= 0.05;
+                transform.scale_mut().x *= 0.5;
+                transform.scale_mut().y *= 0.5;
+                builder
+                    .with(transform)
+                    .with(sprite(sprite_sheet, 0))
+                    .with(PressurePlate { at, flag, latch })
+                    .build();
+            }
+            MarkerKind::Light(light) => {
+                builder.with(transform).with(light).build();
+            }
+            MarkerKind::Stairs { down } => {
+                transform.translation_mut().z -= 0.05;
+                let frame = if down {
+                    STAIRS_DOWN_SPRITE
+                } else {
+                    STAIRS_UP_SPRITE
+                };
+                builder
+                    .with(transform)
+                    .with(sprite(sprite_sheet, frame))
+                    .with(Stairs { at, down })
+                    .build();
+            }
+            MarkerKind::Chest { item } => {
+                transform.translation_mut().z -= 0.05;
+                transform.scale_mut().x *= 0.7;
+                transform.scale_mut().y *= 0.7;
+                builder
+                    .with(transform)
+                    .with(sprite(sprite_sheet, CHEST_SPRITE))
+                    .with(Chest { at, item })
+                    .build();
+            }
+            MarkerKind::EncounterZone { .. } => {}
+        }
+    }
+}
+
+fn sprite(sprite_sheet: &SpriteSheetHandle, sprite_number: usize) -> SpriteRender {
+    SpriteRender {
+        sprite_sheet: sprite_sheet.clone(),
+        sprite_number,
+    }
+}
+
+/// Snapshot of the current game, if a level and player exist.
+fn current_save(world: &World) -> Option<SaveGame> {
+    let players = world.read_storage::<Player>();
+    let positions = world.read_storage::<Position>();
+    let (_, pos) = (&players, &positions).join().next()?;
+    Some(SaveGame {
+        level: world.read_resource::<Level>().level,
+        position: (pos.0.x, pos.0.y),
+        party: world.read_resource::<Party>().clone(),
+        flags: world.read_resource::<Flags>().clone(),
+        explored: world.read_resource::<Fog>().explored.clone(),
+        clock: Some(*world.read_resource::<WorldClock>()),
+        dungeon: world.read_resource::<CurrentFloor>().0,
+        opened_chests: world.read_resource::<OpenedChests>().clone(),
+    })
+}
+
+/// The game as it will be on arriving at the next dungeon floor down, the
+/// first floor of a new dungeon when taking stairs from the manifest's
+/// levels.
+fn descend(world: &World) -> Option<SaveGame> {
+    let mut save = current_save(world)?;
+    let floor = match save.dungeon {
+        Some(floor) => floor.below(),
+        None => DungeonFloor::new(world.write_resource::<Rng>().next_u64()),
+    };
+    let level = dungeon::generate(&world.read_resource::<DungeonConfig>(), floor);
+    save.level = level.level;
+    save.position = dungeon::arrival(&level)?;
+    save.dungeon = Some(floor);
+    // Nothing seen yet of what may be a different dungeon than last time
+    save.explored.remove(&level.level);
+    save.opened_chests.0.remove(&level.level);
+    log::info!("Down to {}", level.name);
+    Some(save)
+}
+
+/// Writes `save` over the last save, unless the game has been won by then:
+/// continuing it would only roll the credits again.
+fn autosave(world: &World, save: &SaveGame) {
+    if world.read_resource::<VictoryCondition>().is_met(&save.flags) {
+        return;
+    }
+    if let Err(e) = save.write_last() {
+        log::error!("Failed to save game: {}", e);
+    }
+}
@@ -0,0 +1,81 @@
+use amethyst::{
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
+    prelude::*,
+};
+
+use super::{
+    game::GameState,
+    menu::{Menu, MenuResult},
+    title::TitleState,
+    RuntimeSystemState,
+};
+use crate::{events::GameStateEvent, save::SaveGame};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GameOverChoice {
+    Reload,
+    Title,
+}
+
+/// Shown when the whole party has fallen in battle.
+#[derive(Default)]
+pub struct GameOverState {
+    save: Option<SaveGame>,
+    menu: Option<Menu<GameOverChoice>>,
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, GameStateEvent> for GameOverState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+        *data.world.write_resource() = RuntimeSystemState::Paused;
+        self.save = SaveGame::load_last();
+
+        let mut menu = Menu::new("Game Over").with_text("Your party has fallen.");
+        if self.save.is_some() {
+            menu = menu.with_item("Reload last save", GameOverChoice::Reload);
+        }
+        let mut menu = menu.with_item("Return to title", GameOverChoice::Title);
+        menu.register(data.world);
+        self.menu = Some(menu);
+    }
+
+    fn on_resume(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+        self.menu
+            .as_mut()
+            .expect("menu built on start")
+            .resume(data.world);
+    }
+
+    fn handle_event(
+        &mut self,
+        _: StateData<'_, GameData<'a, 'b>>,
+        event: GameStateEvent,
+    ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
+        if let GameStateEvent::Window(event) = event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Switch(Box::new(TitleState::default()));
+            }
+        }
+        Trans::None
+    }
+
+    fn update(
+        &mut self,
+        data: StateData<'_, GameData<'a, 'b>>,
+    ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
+        data.data.update(&data.world);
+
+        let menu = self.menu.as_mut().expect("menu built on start");
+        match menu.update(data.world) {
+            Some(MenuResult::Chosen(GameOverChoice::Reload)) => {
+                Trans::Switch(Box::new(GameState::new(data.world, self.save.take())))
+            }
+            Some(MenuResult::Chosen(GameOverChoice::Title)) | Some(MenuResult::Cancelled) => {
+                Trans::Switch(Box::new(TitleState::default()))
+            }
+            None => Trans::None,
+        }
+    }
+}
@@ -1,41 +1,72 @@
 use amethyst::{
     assets::{AssetStorage, Completion, Loader, ProgressCounter},
+    audio::{output::init_output, AudioSink, FlacFormat, Mp3Format, OggFormat, Source, WavFormat},
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
     prelude::*,
-    renderer::{sprite::SpriteSheetHandle, ImageFormat, SpriteSheet, SpriteSheetFormat, Texture},
+    renderer::{ImageFormat, SpriteSheet, SpriteSheetFormat, Texture},
+    utils::application_root_dir,
+    window::Window as GameWindow,
 };
+use imgui::{im_str, Condition, ProgressBar, Window};
+use std::path::Path;
 
-use super::game::GameState;
-use crate::events::GameStateEvent;
+use super::{game::TERRAIN_SHEET, title::TitleState};
+use crate::{
+    assets::{AssetManifest, GameAssets, YamlFormat},
+    data::{ItemList, MonsterList, Terrain},
+    events::GameStateEvent,
+    level::{Level, LevelFormat},
+    settings::Settings,
+};
 
 #[derive(Default)]
 pub struct Loading {
     progress_counter: ProgressCounter,
-    sheet_handle: Option<SpriteSheetHandle>,
+    assets: GameAssets,
+    errors: Vec<String>,
 }
 
 impl<'a, 'b> State<GameData<'a, 'b>, GameStateEvent> for Loading {
-    // TODO: Implement loading logic here
-    // TODO: Add progress bar
-
     // On start will run when this state is initialized. For more
     // state lifecycle hooks, see:
     // https://book.amethyst.rs/stable/concepts/state.html#life-cycle
     fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
-        let loader = &data.world.read_resource::<Loader>();
-        let texture_handle = loader.load(
-            "sprites/dirtgrass.png",
-            ImageFormat::default(),
-            &mut self.progress_counter,
-            &data.world.read_resource::<AssetStorage<Texture>>(),
-        );
+        data.world
+            .read_resource::<Settings>()
+            .apply_window(&data.world.read_resource::<GameWindow>());
+        init_output(data.world);
+        if let Some(mut sink) = data.world.try_fetch_mut::<AudioSink>() {
+            data.world
+                .read_resource::<Settings>()
+                .apply_volume(&mut sink);
+        }
 
-        let sheet_handle = loader.load(
-            "sprites/dirtgrass.ron",
-            SpriteSheetFormat(texture_handle),
-            (),
-            &data.world.read_resource::<AssetStorage<SpriteSheet>>(),
-        );
-        self.sheet_handle = Some(sheet_handle);
+        let manifest_path = application_root_dir()
+            .expect("root dir")
+            .join("resources")
+            .join("manifest.ron");
+        match AssetManifest::load(&manifest_path) {
+            Ok(manifest) => {
+                self.assets = load_manifest(data.world, &manifest, &mut self.progress_counter)
+            }
+            Err(e) => {
+                log::error!("Failed to read {}: {}", manifest_path.display(), e);
+                self.errors.push(format!("{}: {}", manifest_path.display(), e));
+            }
+        }
+    }
+
+    fn handle_event(
+        &mut self,
+        _: StateData<'_, GameData<'a, 'b>>,
+        event: GameStateEvent,
+    ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
+        if let GameStateEvent::Window(event) = event {
+            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Quit;
+            }
+        }
+        Trans::None
     }
 
     fn update(
@@ -43,21 +74,167 @@ impl<'a, 'b> State<GameData<'a, 'b>, GameStateEvent> for Loading {
         data: StateData<'_, GameData<'a, 'b>>,
     ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
         data.data.update(&data.world);
+        if !self.errors.is_empty() {
+            self.draw_errors();
+            return Trans::None;
+        }
         match self.progress_counter.complete() {
             Completion::Complete => {
-                log::info!("Finished loading sprites");
-                Trans::Switch(Box::new(GameState {
-                    sheet_handle: self.sheet_handle.take().expect(
-                        "Expected `sheet_handle` to exist when \
-                         `progress_counter` is complete.",
-                    ),
-                }))
+                log::info!(
+                    "Finished loading {} assets",
+                    self.progress_counter.num_finished()
+                );
+                if !self.assets.has_sprite_sheet(TERRAIN_SHEET) {
+                    self.errors.push(format!(
+                        "Sprite sheet `{}` is missing from the manifest",
+                        TERRAIN_SHEET
+                    ));
+                    return Trans::None;
+                }
+                data.world.insert(self.assets.clone());
+                Trans::Switch(Box::new(TitleState::default()))
             }
             Completion::Failed => {
-                log::error!("Failed to load");
-                Trans::Quit
+                for e in self.progress_counter.errors() {
+                    log::error!(
+                        "Failed to load {} `{}`: {}",
+                        e.asset_type_name,
+                        e.asset_name,
+                        e.error
+                    );
+                    self.errors.push(format!(
+                        "{} `{}`: {}",
+                        e.asset_type_name, e.asset_name, e.error
+                    ));
+                }
+                Trans::None
+            }
+            Completion::Loading => {
+                self.draw_progress();
+                Trans::None
             }
-            Completion::Loading => Trans::None,
         }
     }
 }
+
+impl Loading {
+    fn draw_progress(&self) {
+        let finished = self.progress_counter.num_finished();
+        let total = self.progress_counter.num_assets();
+        let fraction = if total == 0 {
+            0.0
+        } else {
+            finished as f32 / total as f32
+        };
+        amethyst_imgui::with(|ui| {
+            Window::new(im_str!("Loading"))
+                .size([320.0, 60.0], Condition::Always)
+                .position([20.0, 20.0], Condition::Always)
+                .title_bar(false)
+                .resizable(false)
+                .movable(false)
+                .build(ui, || {
+                    ProgressBar::new(ui, fraction)
+                        .overlay_text(&im_str!("Loading {}/{}", finished, total))
+                        .build();
+                });
+        });
+    }
+
+    fn draw_errors(&self) {
+        let errors = &self.errors;
+        amethyst_imgui::with(|ui| {
+            Window::new(im_str!("Loading failed"))
+                .size([480.0, 200.0], Condition::FirstUseEver)
+                .position([20.0, 20.0], Condition::FirstUseEver)
+                .build(ui, || {
+                    for error in errors {
+                        ui.text_wrapped(&im_str!("{}", error));
+                    }
+                    ui.separator();
+                    ui.text("Press Escape to quit.");
+                });
+        });
+    }
+}
+
+/// Queues every asset in `manifest` on the `Loader`, tracking them all with
+/// `progress`.
+fn load_manifest(
+    world: &World,
+    manifest: &AssetManifest,
+    progress: &mut ProgressCounter,
+) -> GameAssets {
+    let loader = world.read_resource::<Loader>();
+    let mut assets = GameAssets::default();
+
+    for entry in &manifest.sprite_sheets {
+        let texture_handle = loader.load(
+            entry.texture.as_str(),
+            ImageFormat::default(),
+            &mut *progress,
+            &world.read_resource::<AssetStorage<Texture>>(),
+        );
+        let sheet_handle = loader.load(
+            entry.sheet.as_str(),
+            SpriteSheetFormat(texture_handle),
+            &mut *progress,
+            &world.read_resource::<AssetStorage<SpriteSheet>>(),
+        );
+        assets.sprite_sheets.insert(entry.name.clone(), sheet_handle);
+    }
+    // Tilesets wait until a level needs them
+    for entry in &manifest.tilesets {
+        assets.tilesets.insert(entry.name.clone(), entry.clone());
+    }
+
+    for path in &manifest.levels {
+        assets.levels.push(loader.load(
+            path.as_str(),
+            LevelFormat,
+            &mut *progress,
+            &world.read_resource::<AssetStorage<Level>>(),
+        ));
+    }
+
+    for path in &manifest.monsters {
+        assets.monsters.push(loader.load(
+            path.as_str(),
+            YamlFormat,
+            &mut *progress,
+            &world.read_resource::<AssetStorage<MonsterList>>(),
+        ));
+    }
+
+    for path in &manifest.items {
+        assets.items.push(loader.load(
+            path.as_str(),
+            YamlFormat,
+            &mut *progress,
+            &world.read_resource::<AssetStorage<ItemList>>(),
+        ));
+    }
+
+    assets.terrain = manifest.terrain.as_ref().map(|path| {
+        loader.load(
+            path.as_str(),
+            YamlFormat,
+            &mut *progress,
+            &world.read_resource::<AssetStorage<Terrain>>(),
+        )
+    });
+
+    let sources = world.read_resource::<AssetStorage<Source>>();
+    for entry in &manifest.audio {
+        let path = entry.path.as_str();
+        let handle = match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("wav") => loader.load(path, WavFormat, &mut *progress, &sources),
+            Some("mp3") => loader.load(path, Mp3Format, &mut *progress, &sources),
+            Some("flac") => loader.load(path, FlacFormat, &mut *progress, &sources),
+            _ => loader.load(path, OggFormat, &mut *progress, &sources),
+        };
+        assets.audio.insert(entry.name.clone(), handle);
+    }
+
+    assets
+}
@@ -0,0 +1,241 @@
+use amethyst::{
+    core::timing::Time,
+    ecs::World,
+    input::{InputEvent, InputHandler, StringBindings},
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+};
+use imgui::{im_str, Condition, Window};
+
+use crate::{
+    controls::{MenuCommand, MenuNavigator},
+    settings::Settings,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuResult<T> {
+    Chosen(T),
+    Cancelled,
+}
+
+/// A vertical list of choices that can be driven by mouse, keyboard or
+/// gamepad. States build one on start and call `update` every frame. The
+/// text above the choices appears at the player's text speed, all at once on
+/// confirming.
+pub struct Menu<T> {
+    title: String,
+    text: Vec<String>,
+    /// `None` to show the text all at once.
+    chars_per_second: Option<f32>,
+    /// How many characters of the text are showing.
+    revealed: f32,
+    items: Vec<(String, T)>,
+    selected: usize,
+    navigator: MenuNavigator,
+    reader: Option<ReaderId<InputEvent<StringBindings>>>,
+}
+
+impl<T: Copy> Menu<T> {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            text: Vec::new(),
+            chars_per_second: None,
+            revealed: 0.0,
+            items: Vec::new(),
+            selected: 0,
+            navigator: MenuNavigator::default(),
+            reader: None,
+        }
+    }
+
+    /// Adds a line of text above the choices.
+    pub fn with_text(mut self, line: &str) -> Self {
+        self.text.push(line.to_string());
+        self
+    }
+
+    pub fn with_item(mut self, label: &str, value: T) -> Self {
+        self.items.push((label.to_string(), value));
+        self
+    }
+
+    /// Replaces the lines of text above the choices.
+    pub fn set_text(&mut self, lines: Vec<String>) {
+        if lines != self.text {
+            self.text = lines;
+            self.revealed = 0.0;
+        }
+    }
+
+    /// Replaces the choices, for menus whose labels show values that change.
+    /// The selection stays where it was, or on the last choice if there are
+    /// fewer now.
+    pub fn set_items(&mut self, items: Vec<(String, T)>) {
+        self.items = items;
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+    }
+
+    /// Starts listening for input events. Must be called from `on_start`.
+    pub fn register(&mut self, world: &mut World) {
+        self.chars_per_second = world
+            .read_resource::<Settings>()
+            .text_speed
+            .chars_per_second();
+        self.reader = Some(
+            world
+                .write_resource::<EventChannel<InputEvent<StringBindings>>>()
+                .register_reader(),
+        );
+    }
+
+    /// Drops the input that arrived while another state was on top of this
+    /// one, which was meant for that state. Must be called from `on_resume`.
+    pub fn resume(&mut self, world: &World) {
+        let channel = world.read_resource::<EventChannel<InputEvent<StringBindings>>>();
+        let reader = self.reader.as_mut().expect("Menu::register not called");
+        channel.read(reader).for_each(drop);
+    }
+
+    pub fn update(&mut self, world: &World) -> Option<MenuResult<T>> {
+        let revealing = self.reveal(world);
+        let mut result = None;
+        for command in self.commands(world) {
+            match command {
+                MenuCommand::Confirm if revealing => self.revealed = f32::INFINITY,
+                MenuCommand::Previous if self.selected > 0 => self.selected -= 1,
+                MenuCommand::Next if self.selected + 1 < self.items.len() => self.selected += 1,
+                MenuCommand::Confirm => {
+                    result = self.items.get(self.selected).map(|(_, v)| MenuResult::Chosen(*v))
+                }
+                MenuCommand::Cancel => result = Some(MenuResult::Cancelled),
+                _ => {}
+            }
+        }
+
+        if let Some(clicked) = self.draw() {
+            self.selected = clicked;
+            result = Some(MenuResult::Chosen(self.items[clicked].1));
+        }
+        result
+    }
+
+    /// Draws the menu but ignores the player, for while the state wants the
+    /// next button pressed for itself.
+    pub fn show(&mut self, world: &World) {
+        self.reveal(world);
+        self.commands(world);
+        self.draw();
+    }
+
+    /// Shows more of the text, and whether some is still hidden.
+    fn reveal(&mut self, world: &World) -> bool {
+        let chars_per_second = match self.chars_per_second {
+            Some(chars_per_second) => chars_per_second,
+            None => return false,
+        };
+        self.revealed += chars_per_second * world.read_resource::<Time>().delta_seconds();
+        let total: usize = self.text.iter().map(|line| line.chars().count()).sum();
+        (self.revealed as usize) < total
+    }
+
+    fn commands(&mut self, world: &World) -> Vec<MenuCommand> {
+        let channel = world.read_resource::<EventChannel<InputEvent<StringBindings>>>();
+        let reader = self.reader.as_mut().expect("Menu::register not called");
+        let axis = world
+            .read_resource::<InputHandler<StringBindings>>()
+            .axis_value("north_south")
+            .unwrap_or(0.0);
+        let delta = world.read_resource::<Time>().delta_seconds();
+        self.navigator.update(axis, channel.read(reader), delta)
+    }
+
+    fn draw(&self) -> Option<usize> {
+        let mut clicked = None;
+        let shown = match self.chars_per_second {
+            Some(_) => self.revealed as usize,
+            None => usize::MAX,
+        };
+        let height = 40.0 + 20.0 * self.text.len() as f32 + 34.0 * self.items.len() as f32;
+        amethyst_imgui::with(|ui| {
+            Window::new(&im_str!("{}", self.title))
+                .size([260.0, height], Condition::Always)
+                .resizable(false)
+                .movable(false)
+                .collapsible(false)
+                .build(ui, || {
+                    for line in revealed(&self.text, shown) {
+                        ui.text(line);
+                    }
+                    for (i, (label, _)) in self.items.iter().enumerate() {
+                        let marker = if i == self.selected { ">" } else { " " };
+                        if ui.button(&im_str!("{} {}", marker, label), [240.0, 30.0]) {
+                            clicked = Some(i);
+                        }
+                    }
+                });
+        });
+        clicked
+    }
+}
+
+/// The first `chars` characters of `lines`, with lines not reached yet left
+/// empty so the menu keeps its size as they appear.
+fn revealed(lines: &[String], chars: usize) -> Vec<&str> {
+    let mut left = chars;
+    lines
+        .iter()
+        .map(|line| {
+            let end = line.char_indices().nth(left).map_or(line.len(), |(i, _)| i);
+            left = left.saturating_sub(line.chars().count());
+            &line[..end]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert(EventChannel::<InputEvent<StringBindings>>::new());
+        world.insert(InputHandler::<StringBindings>::new());
+        world.insert(Time::default());
+        world.insert(Settings::default());
+        world
+    }
+
+    fn press(world: &World, action: &str) {
+        world
+            .write_resource::<EventChannel<InputEvent<StringBindings>>>()
+            .single_write(InputEvent::ActionPressed(action.to_string()));
+    }
+
+    #[test]
+    fn input_while_paused_is_dropped_on_resume() {
+        let mut world = world();
+        let mut menu = Menu::new("Test").with_item("Only", ());
+        menu.register(&mut world);
+
+        // Another state is on top, using the same presses
+        press(&world, "confirm");
+        press(&world, "cancel");
+        menu.resume(&world);
+        assert!(menu.commands(&world).is_empty());
+
+        press(&world, "confirm");
+        assert_eq!(menu.commands(&world), vec![MenuCommand::Confirm]);
+    }
+
+    #[test]
+    fn text_is_revealed_a_character_at_a_time() {
+        let lines = vec!["Your".to_string(), "party".to_string(), "fell".to_string()];
+        assert_eq!(revealed(&lines, 0), vec!["", "", ""]);
+        assert_eq!(revealed(&lines, 6), vec!["Your", "pa", ""]);
+        assert_eq!(revealed(&lines, 9), vec!["Your", "party", ""]);
+        assert_eq!(revealed(&lines, usize::MAX), vec!["Your", "party", "fell"]);
+        let accents = vec!["Ünïcödé".to_string()];
+        assert_eq!(revealed(&accents, 3), vec!["Ünï"]);
+    }
+}
@@ -1,7 +1,13 @@
 use derivative::Derivative;
 
+pub mod credits;
 pub mod game;
+pub mod game_over;
 pub mod loading;
+pub mod menu;
+pub mod options;
+pub mod rebind;
+pub mod title;
 
 #[derive(Clone, Debug, PartialEq, Derivative)]
 #[derivative(Default)]
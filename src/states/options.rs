@@ -0,0 +1,192 @@
+use amethyst::{
+    audio::AudioSink,
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
+    prelude::*,
+    window::Window as GameWindow,
+};
+
+use super::{
+    menu::{Menu, MenuResult},
+    rebind::RebindState,
+};
+use crate::{
+    events::GameStateEvent,
+    grid::MovementMode,
+    settings::{Settings, TextSpeed, WindowMode},
+};
+
+/// The volumes to choose from, quietest first.
+const VOLUMES: [f32; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+
+/// The movement speeds to choose from, slowest first.
+const MOVEMENT_SPEEDS: [f32; 6] = [0.5, 0.75, 1.0, 1.25, 1.5, 2.0];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OptionsChoice {
+    Volume,
+    TextSpeed,
+    MovementSpeed,
+    MovementMode,
+    WindowMode,
+    Bindings,
+    Save,
+    Back,
+}
+
+/// Edits a copy of the `Settings` resource, writing it back to the user's
+/// settings file on save. Choosing a setting steps it to its next value.
+#[derive(Default)]
+pub struct OptionsState {
+    settings: Settings,
+    menu: Option<Menu<OptionsChoice>>,
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, GameStateEvent> for OptionsState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+        self.settings = data.world.read_resource::<Settings>().clone();
+
+        let mut menu = Menu::new("Options");
+        menu.set_items(self.items());
+        menu.register(data.world);
+        self.menu = Some(menu);
+    }
+
+    fn on_resume(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+        // The rebinding screen saves its own changes
+        self.settings.bindings = data.world.read_resource::<Settings>().bindings.clone();
+        self.menu
+            .as_mut()
+            .expect("menu built on start")
+            .resume(data.world);
+    }
+
+    fn handle_event(
+        &mut self,
+        _: StateData<'_, GameData<'a, 'b>>,
+        event: GameStateEvent,
+    ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
+        if let GameStateEvent::Window(event) = event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Pop;
+            }
+        }
+        Trans::None
+    }
+
+    fn update(
+        &mut self,
+        data: StateData<'_, GameData<'a, 'b>>,
+    ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
+        data.data.update(&data.world);
+
+        let menu = self.menu.as_mut().expect("menu built on start");
+        let choice = match menu.update(data.world) {
+            Some(MenuResult::Chosen(choice)) => choice,
+            Some(MenuResult::Cancelled) => OptionsChoice::Back,
+            None => return Trans::None,
+        };
+        let settings = &mut self.settings;
+        match choice {
+            OptionsChoice::Volume => settings.volume = next(&VOLUMES, settings.volume),
+            OptionsChoice::TextSpeed => {
+                settings.text_speed = match settings.text_speed {
+                    TextSpeed::Slow => TextSpeed::Normal,
+                    TextSpeed::Normal => TextSpeed::Fast,
+                    TextSpeed::Fast => TextSpeed::Instant,
+                    TextSpeed::Instant => TextSpeed::Slow,
+                };
+            }
+            OptionsChoice::MovementSpeed => {
+                settings.movement_speed = next(&MOVEMENT_SPEEDS, settings.movement_speed)
+            }
+            OptionsChoice::MovementMode => {
+                settings.movement_mode = match settings.movement_mode {
+                    MovementMode::FourWay => MovementMode::EightWay,
+                    MovementMode::EightWay => MovementMode::FourWay,
+                };
+            }
+            OptionsChoice::WindowMode => {
+                settings.window_mode = match settings.window_mode {
+                    WindowMode::Windowed => WindowMode::Borderless,
+                    WindowMode::Borderless => WindowMode::Fullscreen,
+                    WindowMode::Fullscreen => WindowMode::Windowed,
+                };
+            }
+            OptionsChoice::Bindings => return Trans::Push(Box::new(RebindState::default())),
+            OptionsChoice::Save => {
+                if let Err(e) = settings.write_user() {
+                    log::error!("Failed to save settings: {}", e);
+                }
+                settings.apply_window(&data.world.read_resource::<GameWindow>());
+                if let Some(mut sink) = data.world.try_fetch_mut::<AudioSink>() {
+                    settings.apply_volume(&mut sink);
+                }
+                data.world.insert(settings.clone());
+                return Trans::Pop;
+            }
+            OptionsChoice::Back => return Trans::Pop,
+        }
+        let items = self.items();
+        self.menu
+            .as_mut()
+            .expect("menu built on start")
+            .set_items(items);
+        Trans::None
+    }
+}
+
+impl OptionsState {
+    /// The menu's choices, labelled with the settings as they are now.
+    fn items(&self) -> Vec<(String, OptionsChoice)> {
+        let settings = &self.settings;
+        let text_speed = match settings.text_speed {
+            TextSpeed::Slow => "Slow",
+            TextSpeed::Normal => "Normal",
+            TextSpeed::Fast => "Fast",
+            TextSpeed::Instant => "Instant",
+        };
+        let movement = match settings.movement_mode {
+            MovementMode::FourWay => "Four directions",
+            MovementMode::EightWay => "Eight directions",
+        };
+        let window = match settings.window_mode {
+            WindowMode::Windowed => "Windowed",
+            WindowMode::Borderless => "Borderless",
+            WindowMode::Fullscreen => "Fullscreen",
+        };
+        vec![
+            (
+                format!("Volume: {}%", (settings.volume * 100.0).round()),
+                OptionsChoice::Volume,
+            ),
+            (
+                format!("Text speed: {}", text_speed),
+                OptionsChoice::TextSpeed,
+            ),
+            (
+                format!("Movement speed: {}x", settings.movement_speed),
+                OptionsChoice::MovementSpeed,
+            ),
+            (
+                format!("Movement: {}", movement),
+                OptionsChoice::MovementMode,
+            ),
+            (format!("Window: {}", window), OptionsChoice::WindowMode),
+            ("Key bindings".to_string(), OptionsChoice::Bindings),
+            ("Save".to_string(), OptionsChoice::Save),
+            ("Back".to_string(), OptionsChoice::Back),
+        ]
+    }
+}
+
+/// The value after `current` in `values`, round to the first after the last.
+fn next(values: &[f32], current: f32) -> f32 {
+    values
+        .iter()
+        .cloned()
+        .find(|&value| value > current + 0.01)
+        .unwrap_or(values[0])
+}
@@ -0,0 +1,212 @@
+use amethyst::{
+    input::{
+        is_close_requested, is_key_down, Bindings, Button, InputEvent, InputHandler,
+        StringBindings, VirtualKeyCode,
+    },
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+};
+
+use super::menu::{Menu, MenuResult};
+use crate::{
+    bindings::{self, BindingSlot},
+    events::GameStateEvent,
+    settings::Settings,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RebindChoice {
+    /// The slot at this index of `bindings::slots`.
+    Capture(usize),
+    Swap,
+    KeepOld,
+    Save,
+    Back,
+}
+
+/// Lists every axis side and action binding, capturing the next key or
+/// controller button pressed to replace one.
+#[derive(Default)]
+pub struct RebindState {
+    bindings: Bindings<StringBindings>,
+    reader: Option<ReaderId<InputEvent<StringBindings>>>,
+    capturing: Option<BindingSlot>,
+    /// A captured button that is already in use: (slot, button, used by).
+    pending: Option<(BindingSlot, Button, BindingSlot)>,
+    error: Option<String>,
+    menu: Option<Menu<RebindChoice>>,
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, GameStateEvent> for RebindState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+        self.bindings = data
+            .world
+            .read_resource::<InputHandler<StringBindings>>()
+            .bindings
+            .clone();
+        self.reader = Some(
+            data.world
+                .write_resource::<EventChannel<InputEvent<StringBindings>>>()
+                .register_reader(),
+        );
+        let mut menu = Menu::new("Key bindings");
+        menu.register(data.world);
+        self.menu = Some(menu);
+    }
+
+    fn on_resume(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+        self.menu
+            .as_mut()
+            .expect("menu built on start")
+            .resume(data.world);
+    }
+
+    fn handle_event(
+        &mut self,
+        _: StateData<'_, GameData<'a, 'b>>,
+        event: GameStateEvent,
+    ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
+        if let GameStateEvent::Window(event) = event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+            // Escape backs out of a capture before leaving the screen
+            if is_key_down(&event, VirtualKeyCode::Escape)
+                && self.capturing.take().is_none()
+                && self.pending.take().is_none()
+            {
+                return Trans::Pop;
+            }
+        }
+        Trans::None
+    }
+
+    fn update(
+        &mut self,
+        data: StateData<'_, GameData<'a, 'b>>,
+    ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
+        data.data.update(&data.world);
+
+        // Whatever is pressed while listening is the new binding, not a
+        // menu command
+        let listening = self.capturing.is_some();
+        let captured = {
+            let channel = data
+                .world
+                .read_resource::<EventChannel<InputEvent<StringBindings>>>();
+            let reader = self.reader.as_mut().expect("reader registered on start");
+            channel
+                .read(reader)
+                .filter_map(|event| match event {
+                    InputEvent::ButtonPressed(button) => Some(*button),
+                    _ => None,
+                })
+                .filter(|button| match button {
+                    Button::Key(VirtualKeyCode::Escape) | Button::Mouse(_) => false,
+                    _ => true,
+                })
+                .last()
+        };
+        if let (Some(button), Some(slot)) = (captured, self.capturing.take()) {
+            // Combos can't be swapped with, which `apply` reports
+            match bindings::conflict(&self.bindings, &slot, button) {
+                Some(other) if bindings::slots(&self.bindings).contains(&other) => {
+                    self.pending = Some((slot, button, other))
+                }
+                _ => self.apply(&slot, button),
+            }
+        }
+
+        let (text, items) = (self.text(), self.items());
+        let menu = self.menu.as_mut().expect("menu built on start");
+        menu.set_text(text);
+        menu.set_items(items);
+        let choice = if listening {
+            menu.show(data.world);
+            None
+        } else {
+            menu.update(data.world)
+        };
+        let choice = match choice {
+            Some(MenuResult::Chosen(choice)) => Some(choice),
+            Some(MenuResult::Cancelled) if self.pending.is_some() => Some(RebindChoice::KeepOld),
+            Some(MenuResult::Cancelled) => Some(RebindChoice::Back),
+            None => None,
+        };
+        match choice {
+            Some(RebindChoice::Capture(i)) => {
+                self.error = None;
+                self.capturing = bindings::slots(&self.bindings).get(i).cloned();
+            }
+            Some(RebindChoice::Swap) => {
+                if let Some((slot, button, _)) = self.pending.take() {
+                    self.apply(&slot, button);
+                }
+            }
+            Some(RebindChoice::KeepOld) => self.pending = None,
+            Some(RebindChoice::Save) => {
+                data.world
+                    .write_resource::<InputHandler<StringBindings>>()
+                    .bindings = self.bindings.clone();
+                let mut settings = data.world.write_resource::<Settings>();
+                settings.bindings = Some(self.bindings.clone());
+                if let Err(e) = settings.write_user() {
+                    log::error!("Failed to save key bindings: {}", e);
+                }
+                return Trans::Pop;
+            }
+            Some(RebindChoice::Back) => return Trans::Pop,
+            None => {}
+        }
+        Trans::None
+    }
+}
+
+impl RebindState {
+    fn apply(&mut self, slot: &BindingSlot, button: Button) {
+        if let Err(e) = bindings::rebind(&mut self.bindings, slot, button) {
+            log::warn!("Couldn't bind {:?} to {}: {}", button, slot, e);
+            self.error = Some(format!("Couldn't bind {:?} to {}: {}", button, slot, e));
+        }
+    }
+
+    /// What the player is asked, above the bindings.
+    fn text(&self) -> Vec<String> {
+        let mut text = Vec::new();
+        if let Some((_, button, other)) = &self.pending {
+            text.push(format!("{:?} is bound to {}.", button, other));
+        }
+        if let Some(error) = &self.error {
+            text.push(error.clone());
+        }
+        text
+    }
+
+    /// A choice for each binding labelled with its button, or just whether
+    /// to swap while a captured button is already in use.
+    fn items(&self) -> Vec<(String, RebindChoice)> {
+        if let Some((slot, _, _)) = &self.pending {
+            return vec![
+                (format!("Swap it with {}", slot), RebindChoice::Swap),
+                ("Keep the old binding".to_string(), RebindChoice::KeepOld),
+            ];
+        }
+        let mut items: Vec<_> = bindings::slots(&self.bindings)
+            .into_iter()
+            .enumerate()
+            .map(|(i, slot)| {
+                let bound = if self.capturing.as_ref() == Some(&slot) {
+                    "press a key or button...".to_string()
+                } else {
+                    bindings::button(&self.bindings, &slot)
+                        .map(|button| format!("{:?}", button))
+                        .unwrap_or_default()
+                };
+                (format!("{}: {}", slot, bound), RebindChoice::Capture(i))
+            })
+            .collect();
+        items.push(("Save".to_string(), RebindChoice::Save));
+        items.push(("Back".to_string(), RebindChoice::Back));
+        items
+    }
+}
@@ -0,0 +1,83 @@
+use amethyst::{
+    input::{is_close_requested, is_key_down, VirtualKeyCode},
+    prelude::*,
+};
+
+use super::{
+    game::GameState,
+    menu::{Menu, MenuResult},
+    options::OptionsState,
+    RuntimeSystemState,
+};
+use crate::{events::GameStateEvent, save::SaveGame};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TitleChoice {
+    NewGame,
+    Continue,
+    Options,
+    Quit,
+}
+
+#[derive(Default)]
+pub struct TitleState {
+    menu: Option<Menu<TitleChoice>>,
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, GameStateEvent> for TitleState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+        *data.world.write_resource() = RuntimeSystemState::Paused;
+
+        let mut menu = Menu::new("Title").with_item("New Game", TitleChoice::NewGame);
+        if SaveGame::load_last().is_some() {
+            menu = menu.with_item("Continue", TitleChoice::Continue);
+        }
+        let mut menu = menu
+            .with_item("Options", TitleChoice::Options)
+            .with_item("Quit", TitleChoice::Quit);
+        menu.register(data.world);
+        self.menu = Some(menu);
+    }
+
+    fn on_resume(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+        self.menu
+            .as_mut()
+            .expect("menu built on start")
+            .resume(data.world);
+    }
+
+    fn handle_event(
+        &mut self,
+        _: StateData<'_, GameData<'a, 'b>>,
+        event: GameStateEvent,
+    ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
+        if let GameStateEvent::Window(event) = event {
+            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Quit;
+            }
+        }
+        Trans::None
+    }
+
+    fn update(
+        &mut self,
+        data: StateData<'_, GameData<'a, 'b>>,
+    ) -> Trans<GameData<'a, 'b>, GameStateEvent> {
+        data.data.update(&data.world);
+
+        let menu = self.menu.as_mut().expect("menu built on start");
+        match menu.update(data.world) {
+            Some(MenuResult::Chosen(TitleChoice::NewGame)) => {
+                Trans::Switch(Box::new(GameState::new(data.world, None)))
+            }
+            Some(MenuResult::Chosen(TitleChoice::Continue)) => Trans::Switch(Box::new(
+                GameState::new(data.world, SaveGame::load_last()),
+            )),
+            Some(MenuResult::Chosen(TitleChoice::Options)) => {
+                Trans::Push(Box::new(OptionsState::default()))
+            }
+            Some(MenuResult::Chosen(TitleChoice::Quit)) => Trans::Quit,
+            Some(MenuResult::Cancelled) | None => Trans::None,
+        }
+    }
+}
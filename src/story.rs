@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Story progress: named flags set by events and the bosses the party has
+/// beaten.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Flags {
+    pub set: HashSet<String>,
+    pub defeated_bosses: HashSet<String>,
+}
+
+impl Flags {
+    pub fn set(&mut self, flag: &str) {
+        self.set.insert(flag.to_string());
+    }
+
+    pub fn clear(&mut self, flag: &str) {
+        self.set.remove(flag);
+    }
+
+    pub fn is_set(&self, flag: &str) -> bool {
+        self.set.contains(flag)
+    }
+
+    pub fn defeat_boss(&mut self, boss: &str) {
+        self.defeated_bosses.insert(boss.to_string());
+    }
+
+    pub fn is_boss_defeated(&self, boss: &str) -> bool {
+        self.defeated_bosses.contains(boss)
+    }
+}
+
+/// When the game is won, read from `resources/victory.ron`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum VictoryCondition {
+    FlagSet(String),
+    BossDefeated(String),
+    All(Vec<VictoryCondition>),
+    Any(Vec<VictoryCondition>),
+    Never,
+}
+
+impl Default for VictoryCondition {
+    fn default() -> Self {
+        VictoryCondition::Never
+    }
+}
+
+impl VictoryCondition {
+    pub fn is_met(&self, flags: &Flags) -> bool {
+        use self::VictoryCondition::*;
+
+        match self {
+            FlagSet(flag) => flags.is_set(flag),
+            BossDefeated(boss) => flags.is_boss_defeated(boss),
+            All(conditions) => conditions.iter().all(|c| c.is_met(flags)),
+            Any(conditions) => conditions.iter().any(|c| c.is_met(flags)),
+            Never => false,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use amethyst::{
+    core::timing::Time,
+    derive::SystemDesc,
+    ecs::{Entities, Join, Read, ReadStorage, System, SystemData, World, WriteStorage},
+    renderer::SpriteRender,
+};
+
+use crate::{
+    component::{CharacterSprite, Facing, MovingObject},
+    grid::Direction,
+    level::Level,
+};
+
+/// Turns characters the way they are walking and steps through their walk
+/// cycle in time with their `MovingObject`, standing still once it's done.
+#[derive(Debug, SystemDesc, Default)]
+pub struct CharacterAnimationSystem;
+
+impl<'s> System<'s> for CharacterAnimationSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, MovingObject>,
+        WriteStorage<'s, Facing>,
+        WriteStorage<'s, CharacterSprite>,
+        WriteStorage<'s, SpriteRender>,
+        Read<'s, Level>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mobs, mut facings, mut characters, mut sprites, level, time) = data;
+        let now = time.absolute_time_seconds();
+        for (entity, facing, character, sprite) in
+            (&entities, &mut facings, &mut characters, &mut sprites).join()
+        {
+            let walking = match mobs.get(entity).and_then(|mob| mob.segment_at(now)) {
+                Some((from, to, progress)) => {
+                    // The short way round, for steps across an edge that wraps
+                    let offset = level.offset(from.0.xy(), to.0.xy());
+                    if let Some(direction) = Direction::from_offset(offset) {
+                        facing.0 = direction;
+                    }
+                    if character.step_from != Some(from) {
+                        character.step_from = Some(from);
+                        character.strides = character.strides.wrapping_add(1);
+                    }
+                    Some((character.strides, progress))
+                }
+                None => {
+                    character.step_from = None;
+                    None
+                }
+            };
+            let sprite_number = character.sheet.sprite_number(facing.0, walking);
+            if sprite.sprite_number != sprite_number {
+                sprite.sprite_number = sprite_number;
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use amethyst::{
+    core::{
+        math::{Point3, Vector2},
+        timing::Time,
+        Transform,
+    },
+    derive::SystemDesc,
+    ecs::{Join, Read, ReadExpect, ReadStorage, System, SystemData, World, Write, WriteStorage},
+    renderer::Camera,
+    utils::ortho_camera::CameraOrtho,
+    window::ScreenDimensions,
+};
+
+use crate::{
+    camera::{clamp_to_bounds, follow, nearest_repeat, pan_position, CameraConfig, CameraScript},
+    chunk::MapGrid,
+    component::Player,
+};
+
+/// Moves the camera towards the player, or along the scripted pans while
+/// there are any, without ever showing past the edges of the map.
+#[derive(Debug, SystemDesc, Default)]
+pub struct CameraSystem {
+    /// Where the current pan started, and for how long it has been going.
+    pan: Option<(Vector2<f32>, f32)>,
+}
+
+impl<'s> System<'s> for CameraSystem {
+    type SystemData = (
+        ReadStorage<'s, Camera>,
+        ReadStorage<'s, CameraOrtho>,
+        ReadStorage<'s, Player>,
+        WriteStorage<'s, Transform>,
+        Option<Read<'s, MapGrid>>,
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, CameraConfig>,
+        Write<'s, CameraScript>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (cameras, orthos, players, mut transforms, grid, screen, config, mut script, time) =
+            data;
+        let grid = match grid {
+            Some(grid) => grid,
+            None => return,
+        };
+        let delta_seconds = time.delta_seconds();
+        let target = (&players, &transforms)
+            .join()
+            .next()
+            .map(|(_, transform)| transform.translation().xy());
+
+        let (_, ortho, transform) = match (&cameras, &orthos, &mut transforms).join().next() {
+            Some(camera) => camera,
+            None => return,
+        };
+        // Stepping off one edge of a map that wraps lands on the other, so
+        // jump to the same view of the map there
+        let repeat = grid.repeat();
+        let current = match target {
+            Some(target) => nearest_repeat(transform.translation().xy(), target, repeat),
+            None => transform.translation().xy(),
+        };
+
+        let next = if let Some(pan) = script.pans.front().cloned() {
+            let (from, elapsed) = self.pan.get_or_insert((current, 0.0));
+            *elapsed += delta_seconds;
+            let to = grid.to_world(&Point3::new(pan.to.0, pan.to.1, 0)).xy();
+            let to = nearest_repeat(to, *from, repeat);
+            let t = if pan.secs > 0.0 {
+                *elapsed / pan.secs
+            } else {
+                1.0
+            };
+            let next = pan_position(*from, to, t);
+            if *elapsed >= pan.secs + pan.hold_secs {
+                script.pans.pop_front();
+                self.pan = None;
+            }
+            next
+        } else if let Some(target) = target {
+            follow(current, target, &config, delta_seconds)
+        } else {
+            current
+        };
+
+        let (left, right, bottom, top) = ortho.camera_offsets(screen.aspect_ratio());
+        let half_view = Vector2::new((right - left).abs() / 2.0, (top - bottom).abs() / 2.0);
+        let (min, max) = world_bounds(&grid);
+        let next = clamp_to_bounds(next, half_view, min, max);
+        transform.set_translation_x(next.x);
+        transform.set_translation_y(next.y);
+    }
+}
+
+/// The corners of the map in world space, from the outer edges of its
+/// corner tiles. Unbounded along edges that wrap.
+fn world_bounds(grid: &MapGrid) -> (Vector2<f32>, Vector2<f32>) {
+    let dimensions = grid.dimensions();
+    let tile = grid.tile_dimensions();
+    let first = grid.to_world(&Point3::new(0, 0, 0));
+    let last = grid.to_world(&Point3::new(
+        dimensions.x.saturating_sub(1),
+        dimensions.y.saturating_sub(1),
+        0,
+    ));
+    let half_tile = Vector2::new(tile.x as f32 / 2.0, tile.y as f32 / 2.0);
+    let mut min = Vector2::new(first.x.min(last.x), first.y.min(last.y)) - half_tile;
+    let mut max = Vector2::new(first.x.max(last.x), first.y.max(last.y)) + half_tile;
+    let topology = grid.topology();
+    if topology.wraps_x() {
+        min.x = std::f32::NEG_INFINITY;
+        max.x = std::f32::INFINITY;
+    }
+    if topology.wraps_y() {
+        min.y = std::f32::NEG_INFINITY;
+        max.y = std::f32::INFINITY;
+    }
+    (min, max)
+}
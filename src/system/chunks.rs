@@ -0,0 +1,193 @@
+use amethyst::{
+    core::{
+        math::{Point3, Vector2, Vector3},
+        Parent, Transform,
+    },
+    derive::SystemDesc,
+    ecs::{
+        Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, SystemData, World, Write,
+        WriteStorage,
+    },
+    renderer::{sprite::SpriteSheetHandle, Camera},
+    tiles::Map,
+    utils::ortho_camera::CameraOrtho,
+    window::ScreenDimensions,
+};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    autotile::Autotiles,
+    chunk::{MapGrid, CHUNK_SIZE},
+    level::Level,
+    states::game::{GameTile, TerrainMap, TileMap},
+};
+
+/// Chunks past the edge of the view that are built ahead of the camera.
+const PRELOAD_CHUNKS: f32 = 1.0;
+
+/// Chunks past the edge of the view a built chunk may drift before it is
+/// dropped, so walking back and forth over a boundary doesn't rebuild it.
+const KEEP_CHUNKS: f32 = 2.0;
+
+/// Builds a tilemap per layer for each chunk of the level near the camera,
+/// and deletes those the camera has left behind, so only a screenful or so
+/// of the map is ever in memory however big the level is. The autotile
+/// sprites are worked out for the same chunks.
+#[derive(Debug, SystemDesc, Default)]
+pub struct ChunkStreamingSystem {
+    /// The map the chunks were built for, its grid and its layers' sheets.
+    root: Option<Entity>,
+    grid: Option<MapGrid>,
+    sheets: Vec<Option<SpriteSheetHandle>>,
+    /// Each built chunk's tilemap entities, one per layer.
+    built: HashMap<(i32, i32), Vec<Entity>>,
+}
+
+impl<'s> System<'s> for ChunkStreamingSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Camera>,
+        ReadStorage<'s, CameraOrtho>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, TileMap>,
+        WriteStorage<'s, Parent>,
+        Option<Read<'s, MapGrid>>,
+        Option<Read<'s, TerrainMap>>,
+        Option<Write<'s, Autotiles>>,
+        Read<'s, Level>,
+        ReadExpect<'s, ScreenDimensions>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            cameras,
+            orthos,
+            mut transforms,
+            mut tilemaps,
+            mut parents,
+            grid,
+            terrain_map,
+            autotiles,
+            level,
+            screen,
+        ) = data;
+        let (grid, terrain_map) = match (grid, terrain_map) {
+            (Some(grid), Some(terrain_map)) => (grid, terrain_map),
+            _ => return,
+        };
+
+        // A new map means the old chunks went with the old level, and a
+        // reloaded one may be a new size or drawn from other tilesets
+        if self.root != Some(terrain_map.root)
+            || self.grid.as_ref() != Some(&*grid)
+            || self.sheets != terrain_map.sheets
+        {
+            for entity in self.built.drain().flat_map(|(_, layers)| layers) {
+                // Already gone if the game state deleted everything
+                let _ = entities.delete(entity);
+            }
+            self.root = Some(terrain_map.root);
+            self.grid = Some(grid.clone());
+            self.sheets = terrain_map.sheets.clone();
+        }
+
+        let (centre, half_view) = match (&cameras, &orthos, &transforms).join().next() {
+            Some((_, ortho, transform)) => {
+                let (left, right, bottom, top) = ortho.camera_offsets(screen.aspect_ratio());
+                (
+                    transform.translation().xy(),
+                    Vector2::new((right - left).abs() / 2.0, (top - bottom).abs() / 2.0),
+                )
+            }
+            None => return,
+        };
+        let chunk_extent = |chunks: f32| {
+            let tile = grid.tile_dimensions();
+            half_view
+                + Vector2::new(
+                    chunks * (CHUNK_SIZE * tile.x) as f32,
+                    chunks * (CHUNK_SIZE * tile.y) as f32,
+                )
+        };
+
+        let keep: HashSet<(i32, i32)> = grid
+            .chunks_near(centre, chunk_extent(KEEP_CHUNKS))
+            .into_iter()
+            .collect();
+        let far: Vec<(i32, i32)> = self
+            .built
+            .keys()
+            .filter(|chunk| !keep.contains(chunk))
+            .cloned()
+            .collect();
+        for chunk in far {
+            for entity in self.built.remove(&chunk).unwrap_or_default() {
+                entities.delete(entity).expect("Chunk should be alive");
+            }
+        }
+
+        for chunk in grid.chunks_near(centre, chunk_extent(PRELOAD_CHUNKS)) {
+            if self.built.contains_key(&chunk) {
+                continue;
+            }
+            let mut layers = Vec::new();
+            for (layer, sprites) in terrain_map.sheets.iter().enumerate() {
+                let map = chunk_map(chunk, layer, *grid.tile_dimensions(), sprites.clone());
+                let mut transform = Transform::default();
+                // Line the chunk's first tile up with where it is on the level,
+                // with later layers just above the first
+                let offset = grid.chunk_origin(chunk) - map.to_world(&Point3::new(0, 0, 0), None);
+                transform.set_translation(offset + Vector3::new(0.0, 0.0, 0.001 * layer as f32));
+
+                let entity = entities.create();
+                tilemaps
+                    .insert(entity, map)
+                    .expect("Chunk was just created");
+                transforms
+                    .insert(entity, transform)
+                    .expect("Chunk was just created");
+                parents
+                    .insert(
+                        entity,
+                        Parent {
+                            entity: terrain_map.root,
+                        },
+                    )
+                    .expect("Chunk was just created");
+                layers.push(entity);
+            }
+            self.built.insert(chunk, layers);
+        }
+
+        // Rebuilt from scratch whenever the level or terrain is replaced
+        if let Some(mut autotiles) = autotiles {
+            autotiles.retain(|chunk| self.built.contains_key(&chunk));
+            for &chunk in self.built.keys() {
+                autotiles.build_chunk(&level, chunk);
+            }
+        }
+    }
+}
+
+/// A tilemap covering one chunk of one layer.
+fn chunk_map(
+    chunk: (i32, i32),
+    layer: usize,
+    tile_dimensions: Vector3<u32>,
+    sprites: Option<SpriteSheetHandle>,
+) -> TileMap {
+    let mut map = TileMap::new(
+        Vector3::new(CHUNK_SIZE, CHUNK_SIZE, 1),
+        tile_dimensions,
+        sprites,
+    );
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            if let Some(tile) = map.get_mut(&Point3::new(x, y, 0)) {
+                *tile = GameTile::new(layer, chunk);
+            }
+        }
+    }
+    map
+}
@@ -0,0 +1,123 @@
+use amethyst::{
+    core::{
+        math::{Point3, Vector3},
+        Transform,
+    },
+    derive::SystemDesc,
+    ecs::{Entities, Join, Read, ReadExpect, ReadStorage, System, SystemData, World, WriteStorage},
+    input::{InputHandler, StringBindings},
+    renderer::Camera,
+    window::ScreenDimensions,
+};
+use std::collections::HashSet;
+use winit::MouseButton;
+
+use crate::{
+    chunk::MapGrid,
+    component::{FollowPath, MovingObject, Player, Position},
+    data::Terrain,
+    level::Level,
+    pathfinding::PathFinder,
+    settings::Settings,
+};
+
+/// Sends the player walking to the tile under the cursor when the left
+/// mouse button is clicked.
+#[derive(Debug, SystemDesc, Default)]
+pub struct ClickMoveSystem {
+    was_down: bool,
+}
+
+impl<'s> System<'s> for ClickMoveSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Player>,
+        ReadStorage<'s, Position>,
+        ReadStorage<'s, MovingObject>,
+        WriteStorage<'s, FollowPath>,
+        ReadStorage<'s, Camera>,
+        ReadStorage<'s, Transform>,
+        Option<Read<'s, MapGrid>>,
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, InputHandler<StringBindings>>,
+        Read<'s, Level>,
+        Read<'s, Terrain>,
+        Read<'s, Settings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            players,
+            positions,
+            mobs,
+            mut paths,
+            cameras,
+            transforms,
+            grid,
+            screen,
+            input,
+            level,
+            terrain,
+            settings,
+        ) = data;
+
+        let down = input.mouse_button_is_down(MouseButton::Left);
+        let clicked = down && !self.was_down;
+        self.was_down = down;
+        if !clicked {
+            return;
+        }
+        let mouse = match input.mouse_position() {
+            Some(mouse) => mouse,
+            None => return,
+        };
+        let (camera, camera_transform) = match (&cameras, &transforms).join().next() {
+            Some(camera) => camera,
+            None => return,
+        };
+        let grid = match grid {
+            Some(grid) => grid,
+            None => return,
+        };
+
+        let world_point = camera.projection().screen_to_world_point(
+            Point3::new(mouse.0, mouse.1, 0.0),
+            screen.diagonal(),
+            camera_transform,
+        );
+        let goal = match grid.to_tile(&Vector3::new(world_point.x, world_point.y, 0.0)) {
+            Some(goal) => goal,
+            None => return,
+        };
+
+        let mode = level.movement.unwrap_or(settings.movement_mode);
+        for (entity, _, pos) in (&entities, &players, &positions).join() {
+            let occupied: HashSet<(u32, u32)> = (&entities, &positions)
+                .join()
+                .filter(|(other, _)| *other != entity)
+                .map(|(_, other)| (other.0.x, other.0.y))
+                .collect();
+            // Plan from where the current step ends
+            let from = mobs.get(entity).map(|mob| mob.end_p).unwrap_or(*pos);
+            let path = PathFinder::new(&level)
+                .with_mode(mode)
+                .with_terrain(&terrain)
+                .with_occupied(occupied)
+                .find(from.0, goal);
+            match path {
+                Some(path) => {
+                    paths
+                        .insert(
+                            entity,
+                            FollowPath {
+                                steps: path.into_iter().map(Position).collect(),
+                            },
+                        )
+                        .expect("Player entity should be alive");
+                }
+                None => log::debug!("No path from {:?} to {:?}", from.0, goal),
+            }
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use amethyst::{
+    core::timing::Time,
+    derive::SystemDesc,
+    ecs::{Join, Read, ReadStorage, System, SystemData, World, Write},
+};
+
+use crate::{
+    clock::{ClockConfig, WorldClock},
+    component::{Player, Position},
+};
+
+/// Moves the world clock on with real time and with each step the player
+/// takes.
+#[derive(Debug, SystemDesc, Default)]
+pub struct ClockSystem {
+    last: Option<Position>,
+}
+
+impl<'s> System<'s> for ClockSystem {
+    type SystemData = (
+        ReadStorage<'s, Player>,
+        ReadStorage<'s, Position>,
+        Read<'s, ClockConfig>,
+        Write<'s, WorldClock>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (players, positions, config, mut clock, time): Self::SystemData) {
+        let mut minutes = time.delta_seconds() * config.minutes_per_second;
+        if let Some((_, pos)) = (&players, &positions).join().next() {
+            match self.last.replace(*pos) {
+                Some(last) if last != *pos => minutes += config.minutes_per_step,
+                _ => {}
+            }
+        }
+        clock.advance(minutes);
+    }
+}
@@ -0,0 +1,83 @@
+use amethyst::{
+    derive::SystemDesc,
+    ecs::{Entities, Join, Read, ReadStorage, System, SystemData, World, Write},
+    shrev::EventChannel,
+};
+
+use crate::{
+    component::{Chest, Player, Position, Stairs},
+    dungeon::OpenedChests,
+    events::GameEvent,
+    level::Level,
+    party::Party,
+    states::game::GameSession,
+};
+
+/// Opens chests the player steps onto, and takes the party down when they
+/// step onto stairs down.
+#[derive(Debug, SystemDesc, Default)]
+pub struct DungeonSystem {
+    /// The game the player was last seen in, and where.
+    last: Option<(GameSession, Position)>,
+}
+
+impl<'s> System<'s> for DungeonSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Player>,
+        ReadStorage<'s, Position>,
+        ReadStorage<'s, Stairs>,
+        ReadStorage<'s, Chest>,
+        Read<'s, GameSession>,
+        Read<'s, Level>,
+        Write<'s, Party>,
+        Write<'s, OpenedChests>,
+        Write<'s, EventChannel<GameEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            players,
+            positions,
+            stairs,
+            chests,
+            session,
+            level,
+            mut party,
+            mut opened,
+            mut events,
+        ) = data;
+        let pos = match (&players, &positions).join().next() {
+            Some((_, pos)) => *pos,
+            None => return,
+        };
+        // Only on arriving at a new tile, not on spawning into a new game
+        match self.last.replace((*session, pos)) {
+            Some(last) if last.0 == *session && last.1 != pos => {}
+            _ => return,
+        }
+
+        for (entity, chest) in (&entities, &chests).join() {
+            if chest.at != pos {
+                continue;
+            }
+            match &chest.item {
+                Some(item) => {
+                    log::info!("Found a {}!", item);
+                    party.items.push(item.clone());
+                }
+                None => log::info!("The chest is empty."),
+            }
+            opened.open(level.level, (pos.0.x, pos.0.y));
+            entities.delete(entity).expect("Chest should be alive");
+        }
+
+        if (&stairs)
+            .join()
+            .any(|stairs| stairs.down && stairs.at == pos)
+        {
+            events.single_write(GameEvent::Descend);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use amethyst::{
+    derive::SystemDesc,
+    ecs::{Read, System, SystemData, World, Write},
+    shrev::EventChannel,
+};
+
+use crate::{
+    events::GameEvent,
+    party::Party,
+    story::{Flags, VictoryCondition},
+};
+
+/// Watches the party and story flags and tells the running state when the
+/// game has been lost or won.
+#[derive(Debug, SystemDesc, Default)]
+pub struct EndConditionSystem;
+
+impl<'s> System<'s> for EndConditionSystem {
+    type SystemData = (
+        Read<'s, Party>,
+        Read<'s, Flags>,
+        Read<'s, VictoryCondition>,
+        Write<'s, EventChannel<GameEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (party, flags, victory, mut events) = data;
+        if party.is_defeated() {
+            events.single_write(GameEvent::GameOver);
+        } else if victory.is_met(&flags) {
+            events.single_write(GameEvent::Victory);
+        }
+    }
+}
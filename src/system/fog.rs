@@ -0,0 +1,86 @@
+use amethyst::{
+    core::Hidden,
+    derive::SystemDesc,
+    ecs::{
+        Entities, Entity, Join, Read, ReadStorage, System, SystemData, World, Write, WriteStorage,
+    },
+};
+
+use crate::{
+    component::{Player, Position, PressurePlate},
+    data::ItemList,
+    fov::{Fog, TileVisibility},
+    level::Level,
+    party::Party,
+    states::game::GameSession,
+};
+
+/// Looks again whenever the player reaches a new tile, and hides whatever
+/// the player can't currently see.
+#[derive(Debug, SystemDesc, Default)]
+pub struct FogSystem {
+    /// The game, level, tile and light radius last looked from, and the
+    /// fog's revision after looking. A new game brings a new fog, whose
+    /// revision starts over.
+    last: Option<((GameSession, i32, Position, u32), u64)>,
+}
+
+impl<'s> System<'s> for FogSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Player>,
+        ReadStorage<'s, Position>,
+        ReadStorage<'s, PressurePlate>,
+        WriteStorage<'s, Hidden>,
+        Read<'s, Level>,
+        Read<'s, Party>,
+        Read<'s, ItemList>,
+        Read<'s, GameSession>,
+        Write<'s, Fog>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, players, positions, plates, mut hidden, level, party, items, session, mut fog): Self::SystemData,
+    ) {
+        let player = match (&players, &positions).join().next() {
+            Some((_, pos)) => *pos,
+            None => return,
+        };
+        let look = (*session, level.level, player, party.light_radius(&items));
+        if self.last != Some((look, fog.revision())) {
+            fog.update(&level, player.0.xy(), look.3);
+            self.last = Some((look, fog.revision()));
+        }
+
+        // NPCs and blocks move on their own, so check them every frame
+        for (entity, pos, _) in (&entities, &positions, !&players).join() {
+            set_hidden(
+                &mut hidden,
+                entity,
+                fog.visibility(pos.0.xy()) != TileVisibility::Visible,
+            );
+        }
+        // Plates stay where they are, so remembering one is enough
+        for (entity, plate) in (&entities, &plates).join() {
+            let at = plate.at.0.xy();
+            set_hidden(
+                &mut hidden,
+                entity,
+                fog.visibility(at) == TileVisibility::Unseen,
+            );
+        }
+    }
+}
+
+fn set_hidden(hidden: &mut WriteStorage<'_, Hidden>, entity: Entity, hide: bool) {
+    if hide {
+        if !hidden.contains(entity) {
+            hidden
+                .insert(entity, Hidden)
+                .expect("Entity should be alive");
+        }
+    } else {
+        hidden.remove(entity);
+    }
+}
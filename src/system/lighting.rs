@@ -0,0 +1,124 @@
+use amethyst::{
+    core::{math::Point2, timing::Time, Transform},
+    derive::SystemDesc,
+    ecs::{
+        Entities, Entity, Join, Read, ReadStorage, System, SystemData, World, Write, WriteStorage,
+    },
+    renderer::{palette::Srgba, resources::Tint},
+};
+
+use crate::{
+    chunk::MapGrid,
+    clock::WorldClock,
+    component::{LightSource, MovingObject, Player, Position, PressurePlate},
+    data::ItemList,
+    level::Level,
+    lighting::Lighting,
+    party::Party,
+};
+
+/// Colour of the light the party's torches and lanterns give off.
+const CARRIED_LIGHT: (f32, f32, f32) = (1.0, 0.8, 0.5);
+
+/// Gathers the level's ambient light for the time of day and every light
+/// on the map into `Lighting`, and tints the sprites standing in it.
+#[derive(Debug, SystemDesc, Default)]
+pub struct LightingSystem;
+
+impl<'s> System<'s> for LightingSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Player>,
+        ReadStorage<'s, Position>,
+        ReadStorage<'s, MovingObject>,
+        ReadStorage<'s, LightSource>,
+        ReadStorage<'s, PressurePlate>,
+        ReadStorage<'s, Transform>,
+        Option<Read<'s, MapGrid>>,
+        WriteStorage<'s, Tint>,
+        Read<'s, Level>,
+        Read<'s, WorldClock>,
+        Read<'s, Party>,
+        Read<'s, ItemList>,
+        Read<'s, Time>,
+        Write<'s, Lighting>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            players,
+            positions,
+            mobs,
+            sources,
+            plates,
+            transforms,
+            grid,
+            mut tints,
+            level,
+            clock,
+            party,
+            items,
+            time,
+            mut lighting,
+        ) = data;
+        let now = time.absolute_time_seconds();
+        let tile_of = |entity: Entity| -> Option<Point2<f32>> {
+            let pos = positions.get(entity)?;
+            Some(match mobs.get(entity).and_then(|mob| mob.segment_at(now)) {
+                Some((from, to, t)) => {
+                    let (from, to) = (tile_point(from), tile_point(to));
+                    from + (to - from) * t
+                }
+                None => tile_point(*pos),
+            })
+        };
+
+        lighting.ambient = level
+            .ambient
+            .as_ref()
+            .map(|ambient| ambient.at(clock.daylight()));
+        lighting.lights.clear();
+
+        let radius = party.light_radius(&items);
+        if radius > 0 {
+            let player = (&entities, &players).join().next();
+            if let Some(at) = player.and_then(|(entity, _)| tile_of(entity)) {
+                let carried = LightSource {
+                    radius: radius as f32,
+                    color: CARRIED_LIGHT,
+                };
+                lighting.lights.push((at, carried));
+            }
+        }
+        for (entity, source, transform) in (&entities, &sources, &transforms).join() {
+            // Lamps on the map have no `Position`, so go by where they're drawn
+            let at = tile_of(entity).or_else(|| {
+                let tile = grid.as_ref()?.to_tile(transform.translation())?;
+                Some(Point2::new(tile.x as f32, tile.y as f32))
+            });
+            if let Some(at) = at {
+                lighting.lights.push((at, *source));
+            }
+        }
+
+        for (entity, _) in (&entities, &positions).join() {
+            if let Some(at) = tile_of(entity) {
+                tint(&mut tints, entity, lighting.light_at(at));
+            }
+        }
+        for (entity, plate) in (&entities, &plates).join() {
+            tint(&mut tints, entity, lighting.light_at(tile_point(plate.at)));
+        }
+    }
+}
+
+fn tile_point(pos: Position) -> Point2<f32> {
+    Point2::new(pos.0.x as f32, pos.0.y as f32)
+}
+
+fn tint(tints: &mut WriteStorage<'_, Tint>, entity: Entity, (r, g, b): (f32, f32, f32)) {
+    tints
+        .insert(entity, Tint(Srgba::new(r, g, b, 1.0)))
+        .expect("Entity should be alive");
+}
@@ -0,0 +1,215 @@
+use amethyst::{
+    assets::{AssetStorage, Handle, Loader},
+    core::{HiddenPropagate, Parent},
+    derive::SystemDesc,
+    ecs::{
+        Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, SystemData, World,
+        WriteStorage,
+    },
+    input::{InputHandler, StringBindings},
+    renderer::{
+        rendy::{
+            hal::{
+                format::Format,
+                image::{Filter, Kind, SamplerInfo, ViewKind, WrapMode},
+            },
+            texture::TextureBuilder,
+        },
+        types::TextureData,
+        Texture,
+    },
+    ui::{Anchor, UiImage, UiTransform},
+};
+
+use crate::{
+    component::{Npc, Player, Position, PressurePlate, Pushable},
+    fov::{Fog, TileVisibility},
+    level::Level,
+    minimap::{render, scale, texture_size},
+};
+
+const PLAYER_DOT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const NPC_DOT: [f32; 4] = [1.0, 0.2, 0.2, 1.0];
+const BLOCK_DOT: [f32; 4] = [0.8, 0.6, 0.3, 1.0];
+const PLATE_DOT: [f32; 4] = [0.3, 0.8, 1.0, 1.0];
+
+/// Space between the minimap and the corner of the screen, in pixels.
+const MARGIN: f32 = 8.0;
+
+/// Draws the level into a texture in the top right corner, redrawn only
+/// when the level or the explored tiles change, with dots on top for the
+/// player and whatever else they can see. `minimap` shows and hides it.
+#[derive(Debug, SystemDesc, Default)]
+pub struct MinimapSystem {
+    root: Option<Entity>,
+    dots: Vec<Entity>,
+    /// The level number, size and fog revision last drawn.
+    drawn: Option<(i32, usize, usize, u64)>,
+    hidden: bool,
+    toggle_was_down: bool,
+}
+
+impl<'s> System<'s> for MinimapSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Player>,
+        ReadStorage<'s, Npc>,
+        ReadStorage<'s, Pushable>,
+        ReadStorage<'s, PressurePlate>,
+        ReadStorage<'s, Position>,
+        WriteStorage<'s, UiTransform>,
+        WriteStorage<'s, UiImage>,
+        WriteStorage<'s, Parent>,
+        WriteStorage<'s, HiddenPropagate>,
+        Read<'s, Level>,
+        Read<'s, Fog>,
+        Read<'s, InputHandler<StringBindings>>,
+        ReadExpect<'s, Loader>,
+        Read<'s, AssetStorage<Texture>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            players,
+            npcs,
+            pushables,
+            plates,
+            positions,
+            mut transforms,
+            mut images,
+            mut parents,
+            mut hiddens,
+            level,
+            fog,
+            input,
+            loader,
+            textures,
+        ) = data;
+
+        // The game state deletes every entity when it stops
+        let root = match self.root.filter(|&root| entities.is_alive(root)) {
+            Some(root) => root,
+            None => {
+                let root = entities.create();
+                self.root = Some(root);
+                self.dots.clear();
+                self.drawn = None;
+                root
+            }
+        };
+
+        let down = input.action_is_down("minimap").unwrap_or(false);
+        if down && !self.toggle_was_down {
+            self.hidden = !self.hidden;
+        }
+        self.toggle_was_down = down;
+        if self.hidden {
+            if !hiddens.contains(root) {
+                hiddens
+                    .insert(root, HiddenPropagate)
+                    .expect("Minimap should be alive");
+            }
+            return;
+        }
+        hiddens.remove(root);
+
+        let scale = scale(level.width, level.height);
+        let drawing = (level.level, level.width, level.height, fog.revision());
+        if self.drawn != Some(drawing) {
+            self.drawn = Some(drawing);
+            let texture = draw(&level, &fog, &loader, &textures);
+            let (width, height) = (level.width as f32 * scale, level.height as f32 * scale);
+            let transform = UiTransform::new(
+                "minimap".to_string(),
+                Anchor::TopRight,
+                Anchor::TopRight,
+                -MARGIN,
+                -MARGIN,
+                10.0,
+                width,
+                height,
+            );
+            transforms
+                .insert(root, transform)
+                .expect("Minimap should be alive");
+            images
+                .insert(root, UiImage::Texture(texture))
+                .expect("Minimap should be alive");
+        }
+
+        let visible = |p: Position| fog.visibility(p.0.xy()) == TileVisibility::Visible;
+        let mut dots = Vec::new();
+        for (_, pos) in (&players, &positions).join() {
+            dots.push((pos.0.xy(), PLAYER_DOT));
+        }
+        for (_, pos) in (&npcs, &positions).join() {
+            if visible(*pos) {
+                dots.push((pos.0.xy(), NPC_DOT));
+            }
+        }
+        for (_, pos) in (&pushables, &positions).join() {
+            if visible(*pos) {
+                dots.push((pos.0.xy(), BLOCK_DOT));
+            }
+        }
+        for plate in (&plates).join() {
+            if fog.visibility(plate.at.0.xy()) != TileVisibility::Unseen {
+                dots.push((plate.at.0.xy(), PLATE_DOT));
+            }
+        }
+
+        // Reuse the dot entities from last frame, making or deleting the
+        // difference
+        while self.dots.len() > dots.len() {
+            let dot = self.dots.pop().expect("Checked the length");
+            entities.delete(dot).expect("Dot should be alive");
+        }
+        while self.dots.len() < dots.len() {
+            let dot = entities.create();
+            parents
+                .insert(dot, Parent { entity: root })
+                .expect("Dot was just created");
+            self.dots.push(dot);
+        }
+        let size = scale.max(3.0);
+        for (&dot, (at, colour)) in self.dots.iter().zip(dots) {
+            let transform = UiTransform::new(
+                "minimap_dot".to_string(),
+                Anchor::BottomLeft,
+                Anchor::Middle,
+                (at.x as f32 + 0.5) * scale,
+                (at.y as f32 + 0.5) * scale,
+                1.0,
+                size,
+                size,
+            );
+            transforms
+                .insert(dot, transform)
+                .expect("Dot should be alive");
+            images
+                .insert(dot, UiImage::SolidColor(colour))
+                .expect("Dot should be alive");
+        }
+    }
+}
+
+/// Uploads the minimap of `level` as a texture, a pixel per tile on levels
+/// that fit and stretched over the rest.
+fn draw(
+    level: &Level,
+    fog: &Fog,
+    loader: &Loader,
+    textures: &AssetStorage<Texture>,
+) -> Handle<Texture> {
+    let (width, height) = texture_size(level.width, level.height);
+    let (width, height) = (width as u32, height as u32);
+    let builder = TextureBuilder::new()
+        .with_kind(Kind::D2(width.max(1), height.max(1), 1, 1))
+        .with_view_kind(ViewKind::D2)
+        .with_data_width(width)
+        .with_data_height(height)
+        .with_sampler_info(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))
+        .with_raw_data(render(level, fog), Format::Rgba8Srgb);
+    loader.load_from_data(TextureData(builder), (), textures)
+}
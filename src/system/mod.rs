@@ -1,26 +1,142 @@
 use amethyst::{
-    core::bundle::SystemBundle, ecs::DispatcherBuilder, ecs::World, prelude::SystemExt,
+    assets::Processor, core::bundle::SystemBundle, ecs::DispatcherBuilder, ecs::World,
+    prelude::SystemExt,
 };
 
-use self::{moving::MovingObjectSystem, player::PlayerSystem};
-use crate::states::RuntimeSystemState;
+use self::{
+    animation::CharacterAnimationSystem, camera::CameraSystem, chunks::ChunkStreamingSystem,
+    click::ClickMoveSystem, clock::ClockSystem, dungeon::DungeonSystem, end::EndConditionSystem,
+    fog::FogSystem, lighting::LightingSystem, minimap::MinimapSystem, moving::MovingObjectSystem,
+    npc::NpcSystem, path::PathFollowSystem, player::PlayerSystem, puzzle::PuzzleSystem,
+    reload::ReloadSystem, terrain::TerrainSystem, tiles::TileAnimationSystem,
+};
+use crate::{
+    data::{ItemList, MonsterList, Terrain},
+    level::Level,
+    states::RuntimeSystemState,
+};
 
+pub mod animation;
+pub mod camera;
+pub mod chunks;
+pub mod click;
+pub mod clock;
+pub mod dungeon;
+pub mod end;
+pub mod fog;
+pub mod lighting;
+pub mod minimap;
 pub mod moving;
+pub mod npc;
+pub mod path;
 pub mod player;
+pub mod puzzle;
+pub mod reload;
+pub mod terrain;
+pub mod tiles;
 
 pub struct GameBundle;
 
 impl SystemBundle<'_, '_> for GameBundle {
     fn build(self, _world: &mut World, dispatcher: &mut DispatcherBuilder) -> amethyst::Result<()> {
+        dispatcher.add(Processor::<Level>::new(), "level_processor", &[]);
+        dispatcher.add(Processor::<MonsterList>::new(), "monster_processor", &[]);
+        dispatcher.add(Processor::<ItemList>::new(), "item_processor", &[]);
+        dispatcher.add(Processor::<Terrain>::new(), "terrain_processor", &[]);
+        dispatcher.add(
+            ReloadSystem::default().pausable(RuntimeSystemState::Running),
+            "reload_system",
+            &[
+                "level_processor",
+                "monster_processor",
+                "item_processor",
+                "terrain_processor",
+            ],
+        );
+        dispatcher.add(
+            TileAnimationSystem::default().pausable(RuntimeSystemState::Running),
+            "tile_animation_system",
+            &[],
+        );
         dispatcher.add(
             PlayerSystem::default().pausable(RuntimeSystemState::Running),
             "player_system",
             &["input_system"],
         );
+        dispatcher.add(
+            ClickMoveSystem::default().pausable(RuntimeSystemState::Running),
+            "click_move_system",
+            &["input_system"],
+        );
+        dispatcher.add(
+            NpcSystem::default().pausable(RuntimeSystemState::Running),
+            "npc_system",
+            &[],
+        );
+        dispatcher.add(
+            PathFollowSystem::default().pausable(RuntimeSystemState::Running),
+            "path_system",
+            &["player_system", "click_move_system", "npc_system"],
+        );
         dispatcher.add(
             MovingObjectSystem::default().pausable(RuntimeSystemState::Running),
             "mob_system",
-            &["player_system"],
+            &["path_system"],
+        );
+        dispatcher.add(
+            CharacterAnimationSystem::default().pausable(RuntimeSystemState::Running),
+            "character_animation_system",
+            &["mob_system"],
+        );
+        dispatcher.add(
+            CameraSystem::default().pausable(RuntimeSystemState::Running),
+            "camera_system",
+            &["mob_system"],
+        );
+        dispatcher.add(
+            ChunkStreamingSystem::default().pausable(RuntimeSystemState::Running),
+            "chunk_streaming_system",
+            &["camera_system"],
+        );
+        dispatcher.add(
+            ClockSystem::default().pausable(RuntimeSystemState::Running),
+            "clock_system",
+            &["mob_system"],
+        );
+        dispatcher.add(
+            LightingSystem::default().pausable(RuntimeSystemState::Running),
+            "lighting_system",
+            &["clock_system"],
+        );
+        dispatcher.add(
+            FogSystem::default().pausable(RuntimeSystemState::Running),
+            "fog_system",
+            &["mob_system"],
+        );
+        dispatcher.add(
+            MinimapSystem::default().pausable(RuntimeSystemState::Running),
+            "minimap_system",
+            &["fog_system"],
+        );
+        dispatcher.add(
+            TerrainSystem::default().pausable(RuntimeSystemState::Running),
+            "terrain_system",
+            &["mob_system"],
+        );
+        dispatcher.add(
+            DungeonSystem::default().pausable(RuntimeSystemState::Running),
+            "dungeon_system",
+            &["mob_system"],
+        );
+        dispatcher.add(
+            PuzzleSystem::default().pausable(RuntimeSystemState::Running),
+            "puzzle_system",
+            &["terrain_system"],
+        );
+        dispatcher.add(
+            EndConditionSystem::default().pausable(RuntimeSystemState::Running),
+            "end_condition_system",
+            &["puzzle_system"],
         );
         Ok(())
     }
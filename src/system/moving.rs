@@ -1,11 +1,9 @@
 use amethyst::{
     core::{timing::Time, SystemDesc, Transform},
     derive::SystemDesc,
-    ecs::{Entities, Join, LazyUpdate, Read, ReadStorage, System, SystemData, World, WriteStorage},
+    ecs::{Entities, Join, Read, System, SystemData, World, WriteStorage},
 };
 
-use std::time::Duration;
-
 use crate::component::{MovingObject, Position};
 
 #[derive(Debug, SystemDesc, Default)]
@@ -15,21 +13,28 @@ impl<'s> System<'s> for MovingObjectSystem {
     type SystemData = (
         Entities<'s>,
         WriteStorage<'s, Transform>,
-        ReadStorage<'s, MovingObject>,
+        WriteStorage<'s, MovingObject>,
         WriteStorage<'s, Position>,
-        Read<'s, LazyUpdate>,
         Read<'s, Time>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut transforms, mobs, mut positions, lazy, time) = data;
+        let (entities, mut transforms, mut mobs, mut positions, time) = data;
         let now = time.absolute_time_seconds();
-        for (e, trans, mob) in (&entities, &mut transforms, &mobs).join() {
+        let mut done = Vec::new();
+        for (e, trans, mob, pos) in (&entities, &mut transforms, &mobs, &mut positions).join() {
             *trans.translation_mut() = mob.interpolate(now);
+            // Multi-tile paths update the position as each waypoint is passed
+            *pos = mob.position_at(now);
             if mob.is_done(now) {
-                positions.get_mut(e).expect("Should have a position").0 = mob.end_p.0;
-                lazy.remove::<MovingObject>(e);
+                *pos = mob.end_p;
+                done.push(e);
             }
         }
+        // Removed right away rather than lazily, so `PlayerSystem` can start
+        // the next step on the very next frame.
+        for e in done {
+            mobs.remove(e);
+        }
     }
 }
@@ -0,0 +1,105 @@
+use amethyst::{
+    core::{math::Point3, timing::Time},
+    derive::SystemDesc,
+    ecs::{Entities, Join, Read, ReadStorage, System, SystemData, World, WriteStorage},
+};
+
+use crate::{
+    clock::{scheduled_goal, WorldClock},
+    component::{FollowPath, MovingObject, Npc, NpcGoal, Player, Position},
+    data::Terrain,
+    level::Level,
+    pathfinding::PathFinder,
+};
+
+/// Seconds between path updates, so chasers follow a moving player without
+/// searching every frame.
+const REPATH_SECS: f32 = 0.5;
+
+/// NPCs give up on a goal after searching this many tiles.
+const NODE_LIMIT: usize = 4096;
+
+/// Points each NPC's `FollowPath` at its goal, or at the goal its schedule
+/// has for the time of day.
+#[derive(Debug, SystemDesc, Default)]
+pub struct NpcSystem {
+    since_update: f32,
+}
+
+impl<'s> System<'s> for NpcSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Npc>,
+        ReadStorage<'s, Player>,
+        ReadStorage<'s, Position>,
+        ReadStorage<'s, MovingObject>,
+        WriteStorage<'s, FollowPath>,
+        Read<'s, Level>,
+        Read<'s, Terrain>,
+        Read<'s, WorldClock>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, npcs, players, positions, mobs, mut paths, level, terrain, clock, time) =
+            data;
+        self.since_update += time.delta_seconds();
+        if self.since_update < REPATH_SECS {
+            return;
+        }
+        self.since_update = 0.0;
+
+        let player = (&players, &positions).join().next().map(|(_, pos)| *pos);
+        // Where everyone is or will be at the end of their step
+        let tiles: Vec<_> = (&entities, &positions)
+            .join()
+            .map(|(entity, pos)| {
+                let pos = mobs.get(entity).map(|mob| mob.end_p).unwrap_or(*pos);
+                (entity, (pos.0.x, pos.0.y))
+            })
+            .collect();
+
+        for (entity, npc, pos) in (&entities, &npcs, &positions).join() {
+            let goal = scheduled_goal(&npc.schedule, clock.hour()).unwrap_or(&npc.goal);
+            let goal = match *goal {
+                NpcGoal::Idle => continue,
+                NpcGoal::WalkTo((x, y)) => Point3::new(x, y, 0),
+                NpcGoal::ChasePlayer => match player {
+                    Some(player) => player.0,
+                    None => continue,
+                },
+            };
+            let from = mobs.get(entity).map(|mob| mob.end_p).unwrap_or(*pos);
+            if from.0.xy() == goal.xy() {
+                paths.remove(entity);
+                continue;
+            }
+
+            let occupied = tiles
+                .iter()
+                .filter(|(other, _)| *other != entity)
+                .map(|(_, tile)| *tile)
+                .collect();
+            let path = PathFinder::new(&level)
+                .with_terrain(&terrain)
+                .with_occupied(occupied)
+                .with_node_limit(NODE_LIMIT)
+                .find(from.0, goal);
+            match path {
+                Some(path) => {
+                    paths
+                        .insert(
+                            entity,
+                            FollowPath {
+                                steps: path.into_iter().map(Position).collect(),
+                            },
+                        )
+                        .expect("NPC entity should be alive");
+                }
+                None => {
+                    paths.remove(entity);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use amethyst::{
+    core::timing::Time,
+    derive::SystemDesc,
+    ecs::{Entities, Join, Read, ReadStorage, System, SystemData, World, WriteStorage},
+    input::{InputHandler, StringBindings},
+};
+use std::{collections::HashSet, time::Duration};
+
+use crate::{
+    chunk::MapGrid,
+    component::{FollowPath, MovingObject, Player, Position},
+    data::Terrain,
+    grid::Direction,
+    level::Level,
+    settings::Settings,
+};
+
+/// Walks entities along their `FollowPath` one tile at a time, chaining
+/// steps the same way `PlayerSystem` does. A path is dropped as soon as its
+/// next tile is blocked or taken, leaving it to whoever set it to find a
+/// new one.
+#[derive(Debug, SystemDesc, Default)]
+pub struct PathFollowSystem;
+
+impl<'s> System<'s> for PathFollowSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, MovingObject>,
+        WriteStorage<'s, FollowPath>,
+        WriteStorage<'s, Position>,
+        ReadStorage<'s, Player>,
+        Option<Read<'s, MapGrid>>,
+        Read<'s, Level>,
+        Read<'s, Terrain>,
+        Read<'s, InputHandler<StringBindings>>,
+        Read<'s, Time>,
+        Read<'s, Settings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut mobs,
+            mut paths,
+            mut positions,
+            players,
+            grid,
+            level,
+            terrain,
+            input,
+            time,
+            settings,
+        ) = data;
+        let grid = match grid {
+            Some(grid) => grid,
+            None => return,
+        };
+
+        // Tiles held by anyone, counting where moving entities are headed
+        let mut occupied: HashSet<(u32, u32)> = (&positions)
+            .join()
+            .map(|pos| (pos.0.x, pos.0.y))
+            .chain((&mobs).join().map(|mob| (mob.end_p.0.x, mob.end_p.0.y)))
+            .collect();
+
+        let running = input.action_is_down("run").unwrap_or(false);
+        let now = time.absolute_time_seconds();
+        let mut finished = Vec::new();
+        for (entity, path, pos) in (&entities, &mut paths, &mut positions).join() {
+            let (from, start_time) = match mobs.get(entity) {
+                Some(mob) if !mob.is_done(now) => continue,
+                Some(mob) => (mob.end_p, mob.end_time()),
+                None => (*pos, now),
+            };
+            let next = match path.steps.pop_front() {
+                Some(next) => next,
+                None => {
+                    finished.push(entity);
+                    continue;
+                }
+            };
+            let target = Direction::ALL
+                .iter()
+                .filter_map(|&direction| level.step(from.0, direction))
+                .find(|target| target.xy() == next.0.xy())
+                .map(Position);
+            let target = match target {
+                Some(target) if !occupied.contains(&(target.0.x, target.0.y)) => target,
+                _ => {
+                    finished.push(entity);
+                    continue;
+                }
+            };
+
+            let tile = level
+                .get_tile(target.0.xy())
+                .expect("Open tiles are in bounds");
+            let secs = if players.contains(entity) {
+                settings.step_secs(terrain.step_secs(tile, running))
+            } else {
+                terrain.step_secs(tile, false)
+            };
+            occupied.insert((target.0.x, target.0.y));
+            *pos = from;
+            mobs.insert(
+                entity,
+                MovingObject::new(
+                    start_time,
+                    Duration::from_secs_f32(secs),
+                    &grid,
+                    from,
+                    target,
+                ),
+            )
+            .expect("Path follower should be alive");
+            if path.steps.is_empty() {
+                finished.push(entity);
+            }
+        }
+        for entity in finished {
+            paths.remove(entity);
+        }
+    }
+}
@@ -1,77 +1,165 @@
 use amethyst::{
-    core::{math::Point3, timing::Time},
+    core::timing::Time,
     derive::SystemDesc,
-    ecs::{Entities, Join, LazyUpdate, Read, ReadStorage, System, SystemData, World},
+    ecs::{Entities, Entity, Join, Read, ReadStorage, System, SystemData, World, WriteStorage},
     input::{InputHandler, StringBindings},
 };
-use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 use crate::{
-    component::{MovingObject, Player, Position},
+    chunk::MapGrid,
+    component::{Facing, FollowPath, MovingObject, Player, Position, Pushable},
+    controls::dominant_axis,
+    data::Terrain,
+    grid::{Direction, MovementMode},
     level::Level,
-    states::game::TileMap,
+    puzzle::push_target,
+    settings::Settings,
 };
 
+/// Starts a step whenever the player is standing still, or is finishing a
+/// step, and a direction is held or was pressed during the last step.
+/// Stepping into a block pushes it along, both moving together.
 #[derive(Debug, SystemDesc, Default)]
-pub struct PlayerSystem;
+pub struct PlayerSystem {
+    /// Direction pressed while a step was in progress.
+    queued: Option<Direction>,
+}
 
 impl<'s> System<'s> for PlayerSystem {
     type SystemData = (
         Entities<'s>,
-        ReadStorage<'s, MovingObject>,
+        WriteStorage<'s, MovingObject>,
+        WriteStorage<'s, FollowPath>,
+        WriteStorage<'s, Facing>,
         ReadStorage<'s, Player>,
-        ReadStorage<'s, Position>,
-        ReadStorage<'s, TileMap>,
+        ReadStorage<'s, Pushable>,
+        WriteStorage<'s, Position>,
+        Option<Read<'s, MapGrid>>,
         Read<'s, Level>,
+        Read<'s, Terrain>,
         Read<'s, InputHandler<StringBindings>>,
-        Read<'s, LazyUpdate>,
         Read<'s, Time>,
+        Read<'s, Settings>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mobs, players, positions, tilemaps, level, input, lazy, time) = data;
-        let tilemap = tilemaps.join().next();
-        if tilemap.is_none() {
-            return;
-        }
-        let tilemap = tilemap.expect("Can't get here");
+        let (
+            entities,
+            mut mobs,
+            mut paths,
+            mut facings,
+            players,
+            pushables,
+            mut positions,
+            grid,
+            level,
+            terrain,
+            input,
+            time,
+            settings,
+        ) = data;
+        let grid = match grid {
+            Some(grid) => grid,
+            None => return,
+        };
 
-        let (d_x, d_y) = (
+        let (x, y) = (
             input.axis_value("east_west").expect("axis should exist"),
             input.axis_value("north_south").expect("axis should exist"),
         );
-        for (entity, _, _, pos) in (&entities, !&mobs, &players, &positions).join() {
-            let duration = Duration::from_millis(200);
-            let mob = if d_x != 0.0 {
-                if !level.is_blocking((*pos + Position(Point3::new(d_x as u32, 0, 0))).0.xy()) {
-                    Some(MovingObject::new(
-                        time.absolute_time_seconds(),
-                        duration,
-                        &tilemap,
-                        *pos,
-                        *pos + Position(Point3::new(d_x as u32, 0, 0)),
-                    ))
-                } else {
-                    None
-                }
-            } else if d_y != 0.0 {
-                if !level.is_blocking((*pos + Position(Point3::new(0, d_y as u32, 0))).0.xy()) {
-                    Some(MovingObject::new(
-                        time.absolute_time_seconds(),
-                        duration,
-                        &tilemap,
-                        *pos,
-                        *pos + Position(Point3::new(0, d_y as u32, 0)),
-                    ))
-                } else {
-                    None
-                }
-            } else {
-                None
+        let mode = level.movement.unwrap_or(settings.movement_mode);
+        let direction = match mode {
+            MovementMode::FourWay => {
+                let (d_x, d_y) = dominant_axis(x, y, settings.stick_deadzone);
+                Direction::from_axes(d_x, d_y)
+            }
+            MovementMode::EightWay if x.hypot(y) > settings.stick_deadzone => {
+                Direction::from_axes_in(mode, x, y)
+            }
+            MovementMode::EightWay => None,
+        };
+        // The latest press wins, and holding a key keeps it queued
+        if direction.is_some() {
+            self.queued = direction;
+        }
+
+        // Who holds each tile, counting where moving entities are headed
+        let mut occupied: HashMap<(u32, u32), Entity> = HashMap::new();
+        for (entity, pos, _) in (&entities, &positions, !&players).join() {
+            occupied.insert((pos.0.x, pos.0.y), entity);
+            if let Some(mob) = mobs.get(entity) {
+                occupied.insert((mob.end_p.0.x, mob.end_p.0.y), entity);
+            }
+        }
+
+        let running = input.action_is_down("run").unwrap_or(false);
+        let now = time.absolute_time_seconds();
+        let mut pushes = Vec::new();
+        for (entity, _, pos) in (&entities, &players, &mut positions).join() {
+            // Walking by hand cancels a click-to-move path
+            if self.queued.is_some() {
+                paths.remove(entity);
+            }
+            // Chain straight on from a step that ends this frame, so there is
+            // no idle frame between steps.
+            let (from, start_time) = match mobs.get(entity) {
+                Some(mob) if !mob.is_done(now) => continue,
+                Some(mob) => (mob.end_p, mob.end_time()),
+                None => (*pos, now),
+            };
+            let direction = match self.queued.take() {
+                Some(direction) => direction,
+                None => continue,
             };
-            if let Some(mob) = mob {
-                lazy.insert(entity, mob);
+            // Turn even when the way is blocked
+            if let Some(facing) = facings.get_mut(entity) {
+                facing.0 = direction;
             }
+            let target = match level.step(from.0, direction) {
+                Some(target) => Position(target),
+                None => continue,
+            };
+            let tile = level
+                .get_tile(target.0.xy())
+                .expect("Open tiles are in bounds");
+            let duration =
+                Duration::from_secs_f32(settings.step_secs(terrain.step_secs(tile, running)));
+
+            if let Some(&other) = occupied.get(&(target.0.x, target.0.y)) {
+                // Only blocks at rest can be pushed, anything else is in the way
+                let block_from = match mobs.get(other) {
+                    _ if !pushables.contains(other) => continue,
+                    Some(mob) if !mob.is_done(now) => continue,
+                    Some(mob) => mob.end_p,
+                    None => target,
+                };
+                let tiles: HashSet<_> = occupied.keys().cloned().collect();
+                let block_to = match push_target(&level, &tiles, block_from.0, direction) {
+                    Some(block_to) => Position(block_to),
+                    None => continue,
+                };
+                mobs.insert(
+                    other,
+                    MovingObject::new(start_time, duration, &grid, block_from, block_to),
+                )
+                .expect("Block entity should be alive");
+                pushes.push((other, block_from));
+            }
+            *pos = from;
+            mobs.insert(
+                entity,
+                MovingObject::new(start_time, duration, &grid, from, target),
+            )
+            .expect("Player entity should be alive");
+        }
+        for (block, from) in pushes {
+            positions
+                .insert(block, from)
+                .expect("Block entity should be alive");
         }
     }
 }
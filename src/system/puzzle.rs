@@ -0,0 +1,101 @@
+use amethyst::{
+    core::Transform,
+    derive::SystemDesc,
+    ecs::{Entities, Join, Read, ReadStorage, System, SystemData, World, Write, WriteStorage},
+    input::{InputHandler, StringBindings},
+};
+use std::collections::HashSet;
+
+use crate::{
+    chunk::MapGrid,
+    component::{FollowPath, MovingObject, Player, Position, PressurePlate, Pushable},
+    puzzle::RoomEntry,
+    story::Flags,
+};
+
+/// Updates pressure plate flags once blocks have finished moving, and puts
+/// the blocks and the player back where they started when `reset` is
+/// pressed.
+#[derive(Debug, SystemDesc, Default)]
+pub struct PuzzleSystem {
+    reset_was_down: bool,
+}
+
+impl<'s> System<'s> for PuzzleSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Pushable>,
+        ReadStorage<'s, PressurePlate>,
+        ReadStorage<'s, Player>,
+        WriteStorage<'s, Position>,
+        WriteStorage<'s, MovingObject>,
+        WriteStorage<'s, FollowPath>,
+        WriteStorage<'s, Transform>,
+        Option<Read<'s, MapGrid>>,
+        Read<'s, RoomEntry>,
+        Read<'s, InputHandler<StringBindings>>,
+        Write<'s, Flags>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            pushables,
+            plates,
+            players,
+            mut positions,
+            mut mobs,
+            mut paths,
+            mut transforms,
+            grid,
+            entry,
+            input,
+            mut flags,
+        ) = data;
+
+        let down = input.action_is_down("reset").unwrap_or(false);
+        let reset = down && !self.reset_was_down;
+        self.reset_was_down = down;
+        if let (true, Some(grid)) = (reset, grid) {
+            let place = |transform: &mut Transform, pos: Position| {
+                let world = grid.to_world(&pos.0);
+                transform.set_translation_x(world.x);
+                transform.set_translation_y(world.y);
+            };
+            for (entity, block, pos, transform) in
+                (&entities, &pushables, &mut positions, &mut transforms).join()
+            {
+                mobs.remove(entity);
+                *pos = block.home;
+                place(transform, block.home);
+            }
+            if let Some(start) = entry.0 {
+                for (entity, _, pos, transform) in
+                    (&entities, &players, &mut positions, &mut transforms).join()
+                {
+                    mobs.remove(entity);
+                    paths.remove(entity);
+                    *pos = start;
+                    place(transform, start);
+                }
+            }
+        }
+
+        // Blocks only count once they have come to rest on a plate
+        let covered: HashSet<(u32, u32)> = (&entities, &pushables, &positions)
+            .join()
+            .filter(|(entity, _, _)| !mobs.contains(*entity))
+            .map(|(_, _, pos)| (pos.0.x, pos.0.y))
+            .collect();
+        for plate in plates.join() {
+            if covered.contains(&(plate.at.0.x, plate.at.0.y)) {
+                if !flags.is_set(&plate.flag) {
+                    log::info!("Pressure plate set {}", plate.flag);
+                    flags.set(&plate.flag);
+                }
+            } else if !plate.latch {
+                flags.clear(&plate.flag);
+            }
+        }
+    }
+}
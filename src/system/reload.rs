@@ -0,0 +1,338 @@
+use amethyst::{
+    assets::{Asset, AssetStorage, Handle, Loader},
+    core::{
+        math::{Point2, Point3, Vector3},
+        Parent, Transform,
+    },
+    derive::SystemDesc,
+    ecs::{
+        Entities, Join, Read, ReadExpect, ReadStorage, System, SystemData, World, Write,
+        WriteStorage,
+    },
+    renderer::{SpriteSheet, Texture},
+    shrev::EventChannel,
+};
+use std::collections::HashMap;
+
+use crate::{
+    assets::GameAssets,
+    autotile::Autotiles,
+    chunk::MapGrid,
+    component::{FollowPath, FromMarker, MovingObject, Player, Position},
+    data::{ItemList, MonsterList, Terrain},
+    dungeon::CurrentFloor,
+    events::GameEvent,
+    fov::Fog,
+    level::Level,
+    states::game::{layer_sheets, TerrainMap},
+    tileset::TileLayers,
+};
+
+/// Picks up levels, terrain, item and monster lists that the asset loader
+/// has reloaded after their files changed, and swaps them in under the
+/// running game: the tiles redraw, the map is rebuilt if its size changed,
+/// whatever the level's markers spawned is spawned again, and the player is
+/// moved off anything that is now solid.
+///
+/// Files that fail to parse are reported by the asset processors, which
+/// keep the last version that worked.
+#[derive(Debug, SystemDesc, Default)]
+pub struct ReloadSystem {
+    /// The version of each asset last swapped in, by asset type and handle
+    /// id.
+    versions: HashMap<(&'static str, u32), u32>,
+}
+
+impl<'s> System<'s> for ReloadSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Player>,
+        ReadStorage<'s, Parent>,
+        ReadStorage<'s, FromMarker>,
+        WriteStorage<'s, Position>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, MovingObject>,
+        WriteStorage<'s, FollowPath>,
+        Read<'s, AssetStorage<Level>>,
+        Read<'s, AssetStorage<Terrain>>,
+        Read<'s, AssetStorage<ItemList>>,
+        Read<'s, AssetStorage<MonsterList>>,
+        Option<Write<'s, GameAssets>>,
+        ReadExpect<'s, Loader>,
+        Read<'s, AssetStorage<Texture>>,
+        Read<'s, AssetStorage<SpriteSheet>>,
+        Read<'s, CurrentFloor>,
+        Write<'s, Level>,
+        Write<'s, Terrain>,
+        Write<'s, ItemList>,
+        Write<'s, MonsterList>,
+        Write<'s, Fog>,
+        Option<Write<'s, MapGrid>>,
+        Option<Write<'s, TerrainMap>>,
+        Option<Write<'s, TileLayers>>,
+        Option<Write<'s, Autotiles>>,
+        Write<'s, EventChannel<GameEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            players,
+            parents,
+            from_markers,
+            mut positions,
+            mut transforms,
+            mut mobs,
+            mut paths,
+            level_storage,
+            terrain_storage,
+            item_storage,
+            monster_storage,
+            assets,
+            loader,
+            textures,
+            sprite_sheets,
+            floor,
+            mut level,
+            mut terrain,
+            mut items,
+            mut monsters,
+            mut fog,
+            grid,
+            terrain_map,
+            layers,
+            autotiles,
+            mut events,
+        ) = data;
+        let mut assets = match assets {
+            Some(assets) => assets,
+            None => return,
+        };
+
+        let mut level_changed = false;
+        for handle in &assets.levels {
+            if !self.changed(&level_storage, handle) {
+                continue;
+            }
+            match level_storage.get(handle) {
+                // Dungeon floors aren't read from files, so never reload
+                Some(reloaded) if reloaded.level == level.level && floor.0.is_none() => {
+                    log::info!("Reloaded {}", reloaded.name);
+                    *level = reloaded.clone();
+                    level_changed = true;
+                }
+                _ => {}
+            }
+        }
+
+        let mut terrain_changed = false;
+        if let Some(handle) = &assets.terrain {
+            if self.changed(&terrain_storage, handle) {
+                if let Some(reloaded) = terrain_storage.get(handle) {
+                    log::info!("Reloaded the terrain");
+                    *terrain = reloaded.clone();
+                    terrain_changed = true;
+                }
+            }
+        }
+
+        let mut items_changed = false;
+        for handle in &assets.items {
+            items_changed |= self.changed(&item_storage, handle);
+        }
+        if items_changed {
+            log::info!("Reloaded the item lists");
+            *items = ItemList::merged(
+                assets
+                    .items
+                    .iter()
+                    .filter_map(|handle| item_storage.get(handle)),
+            );
+        }
+
+        let mut monsters_changed = false;
+        for handle in &assets.monsters {
+            monsters_changed |= self.changed(&monster_storage, handle);
+        }
+        if monsters_changed {
+            log::info!("Reloaded the monster lists");
+            *monsters = MonsterList::merged(
+                assets
+                    .monsters
+                    .iter()
+                    .filter_map(|handle| monster_storage.get(handle)),
+            );
+        }
+
+        if !level_changed && !terrain_changed {
+            return;
+        }
+        let (mut grid, mut terrain_map, mut layers, mut autotiles) =
+            match (grid, terrain_map, layers, autotiles) {
+                (Some(grid), Some(terrain_map), Some(layers), Some(autotiles)) => {
+                    (grid, terrain_map, layers, autotiles)
+                }
+                // No game running to swap them into
+                _ => return,
+            };
+
+        *autotiles = Autotiles::new(&level, &terrain);
+        *layers = TileLayers::build(&level, &terrain);
+        terrain_map.sheets = layer_sheets(&layers, &mut assets, &loader, &textures, &sprite_sheets);
+        fog.invalidate();
+
+        if level_changed {
+            // Markers may have moved, or be inside walls now, so the game
+            // spawns them afresh
+            for (entity, _) in (&entities, &from_markers).join() {
+                entities.delete(entity).expect("Marker should be alive");
+            }
+            events.single_write(GameEvent::LevelReloaded);
+        }
+
+        let resized = MapGrid::new(
+            Vector3::new(level.width as u32, level.height as u32, 1),
+            *grid.tile_dimensions(),
+        )
+        .with_topology(level.topology);
+        if resized != *grid {
+            // Everything on the map keeps its tile, which is somewhere
+            // else in the world now the map's centre has moved
+            let shift = resize_shift(&grid, &resized);
+            for (transform, parent) in (&mut transforms, &parents).join() {
+                if parent.entity == terrain_map.root {
+                    transform.prepend_translation(shift);
+                }
+            }
+            // Steps under way were headed for where tiles used to be
+            for (entity, pos, transform) in (&entities, &positions, &mut transforms).join() {
+                if mobs.remove(entity).is_some() {
+                    let at = resized.to_world(&pos.0);
+                    transform.set_translation_x(at.x);
+                    transform.set_translation_y(at.y);
+                }
+            }
+            *grid = resized;
+        }
+
+        for (entity, _, pos, transform) in
+            (&entities, &players, &mut positions, &mut transforms).join()
+        {
+            let open = match place_to_stand(&level, pos.0.xy()) {
+                Some(open) if open == pos.0.xy() => continue,
+                Some(open) => open,
+                None => {
+                    log::error!("{} has nowhere to stand", level.name);
+                    continue;
+                }
+            };
+            log::warn!("Moved the player off solid ground to {:?}", open);
+            pos.0 = Point3::new(open.x, open.y, pos.0.z);
+            let at = grid.to_world(&pos.0);
+            transform.set_translation_x(at.x);
+            transform.set_translation_y(at.y);
+            mobs.remove(entity);
+            paths.remove(entity);
+        }
+    }
+}
+
+impl ReloadSystem {
+    /// Whether the asset behind `handle` has been reloaded since it was last
+    /// looked at. Not on first sight, when it is whatever the game started
+    /// with.
+    fn changed<A: Asset>(&mut self, storage: &AssetStorage<A>, handle: &Handle<A>) -> bool {
+        self.seen((A::NAME, handle.id()), storage.get_version(handle))
+    }
+
+    /// Remembers `version` for the asset `key`, and whether it differs from
+    /// the version remembered before.
+    fn seen(&mut self, key: (&'static str, u32), version: Option<u32>) -> bool {
+        let version = match version {
+            Some(version) => version,
+            None => return false,
+        };
+        match self.versions.insert(key, version) {
+            Some(last) => last != version,
+            None => false,
+        }
+    }
+}
+
+/// How far everything on the map moves in the world when `old` is resized
+/// to `new`, for each thing to stay on the tile it was on.
+fn resize_shift(old: &MapGrid, new: &MapGrid) -> Vector3<f32> {
+    let origin = Point3::new(0, 0, 0);
+    new.to_world(&origin) - old.to_world(&origin)
+}
+
+/// Where the player at `at` should stand on `level`: right there unless it's
+/// off the map or solid, otherwise the nearest open tile. `None` when the
+/// level has no open tiles.
+fn place_to_stand(level: &Level, at: Point2<u32>) -> Option<Point2<u32>> {
+    let (x, y) = (i64::from(at.x), i64::from(at.y));
+    if level.wrap(x, y) == Some(at) && !level.is_blocking(at) {
+        return Some(at);
+    }
+    level.nearest_open(at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_new_versions_count_as_changed() {
+        let mut system = ReloadSystem::default();
+        let level = ("Level", 1);
+        // What the game started with isn't a reload
+        assert!(!system.seen(level, Some(0)));
+        assert!(!system.seen(level, Some(0)));
+        assert!(system.seen(level, Some(1)));
+        assert!(!system.seen(level, Some(1)));
+        // Not loaded yet, or gone
+        assert!(!system.seen(level, None));
+        assert!(!system.seen(level, Some(1)));
+
+        // Each asset has its own versions
+        assert!(!system.seen(("Level", 2), Some(1)));
+        assert!(!system.seen(("Terrain", 1), Some(5)));
+        assert!(system.seen(level, Some(2)));
+    }
+
+    #[test]
+    fn resizing_keeps_things_on_their_tiles() {
+        let tile = Vector3::new(32, 32, 1);
+        let old = MapGrid::new(Vector3::new(10, 6, 1), tile);
+        for &(width, height) in &[(10, 6), (20, 6), (10, 3), (7, 11)] {
+            let new = MapGrid::new(Vector3::new(width, height, 1), tile);
+            let shift = resize_shift(&old, &new);
+            for &(x, y) in &[(0, 0), (3, 2), (6, 2)] {
+                let p = Point3::new(x, y, 0);
+                assert_eq!(old.to_world(&p) + shift, new.to_world(&p));
+            }
+        }
+    }
+
+    #[test]
+    fn player_is_moved_off_solid_ground() {
+        let level = Level::from_ascii(1, "Test", "..#\n###\n#..").unwrap();
+        // Open tiles are fine as they are
+        assert_eq!(
+            place_to_stand(&level, Point2::new(1, 2)),
+            Some(Point2::new(1, 2))
+        );
+        // Walls aren't, nor is outside a level that has shrunk
+        assert_eq!(
+            place_to_stand(&level, Point2::new(2, 2)),
+            Some(Point2::new(1, 2))
+        );
+        assert_eq!(
+            place_to_stand(&level, Point2::new(9, 0)),
+            Some(Point2::new(2, 0))
+        );
+
+        let walls = Level::from_ascii(1, "Walls", "##\n##").unwrap();
+        assert_eq!(place_to_stand(&walls, Point2::new(0, 0)), None);
+    }
+}
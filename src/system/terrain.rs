@@ -0,0 +1,87 @@
+use amethyst::{
+    derive::SystemDesc,
+    ecs::{Entities, Join, Read, ReadStorage, System, SystemData, World, Write},
+    shrev::EventChannel,
+};
+use std::collections::HashMap;
+
+use crate::{
+    clock::WorldClock,
+    component::{Player, Position},
+    data::Terrain,
+    events::GameEvent,
+    level::Level,
+    party::Party,
+    rng::Rng,
+    states::game::GameSession,
+};
+
+/// Applies the effects of the tile the player has just stepped onto, and
+/// rolls for random encounters.
+#[derive(Debug, SystemDesc, Default)]
+pub struct TerrainSystem {
+    /// Where each player entity last was, by entity id.
+    last: HashMap<u32, Position>,
+    /// The game and level those positions are on. Ids are reused once a new
+    /// game or level has deleted the old entities.
+    on: Option<(GameSession, i32)>,
+}
+
+impl<'s> System<'s> for TerrainSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Player>,
+        ReadStorage<'s, Position>,
+        Read<'s, GameSession>,
+        Read<'s, Level>,
+        Read<'s, Terrain>,
+        Write<'s, Party>,
+        Read<'s, WorldClock>,
+        Write<'s, Rng>,
+        Write<'s, EventChannel<GameEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            players,
+            positions,
+            session,
+            level,
+            terrain,
+            mut party,
+            clock,
+            mut rng,
+            mut events,
+        ) = data;
+        if self.on != Some((*session, level.level)) {
+            self.on = Some((*session, level.level));
+            self.last.clear();
+        }
+        for (entity, _, pos) in (&entities, &players, &positions).join() {
+            // Only on arriving at a new tile, not on spawning
+            match self.last.insert(entity.id(), *pos) {
+                Some(last) if last != *pos => {}
+                _ => continue,
+            }
+            let tile = match level.get_tile(pos.0.xy()) {
+                Ok(tile) => tile,
+                Err(_) => continue,
+            };
+            let damage = terrain.properties(tile).damage;
+            if damage > 0 {
+                for member in party.members.iter_mut() {
+                    member.damage(damage);
+                }
+            }
+
+            if let Some(encounters) = &level.encounters {
+                if level.in_encounter_zone(pos.0.xy()) && rng.chance(encounters.rate) {
+                    if let Some(monster) = encounters.pick(clock.time_of_day(), &mut rng) {
+                        events.single_write(GameEvent::Battle(monster.to_string()));
+                    }
+                }
+            }
+        }
+    }
+}
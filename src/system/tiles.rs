@@ -0,0 +1,52 @@
+use amethyst::{
+    core::timing::Time,
+    derive::SystemDesc,
+    ecs::{Read, System, SystemData, World, Write},
+};
+use std::collections::HashMap;
+
+use crate::{data::Terrain, level::LevelTile};
+
+/// The sprite each kind of tile shows this frame, worked out once per frame
+/// so `GameTile::sprite` is a lookup however big the map is.
+#[derive(Debug, Clone, Default)]
+pub struct TileFrames {
+    sprites: HashMap<LevelTile, usize>,
+}
+
+impl TileFrames {
+    pub fn sprite(&self, tile: LevelTile) -> Option<usize> {
+        self.sprites.get(&tile).cloned()
+    }
+}
+
+/// Advances animated tiles from the terrain data with the game clock.
+#[derive(Debug, SystemDesc, Default)]
+pub struct TileAnimationSystem;
+
+impl<'s> System<'s> for TileAnimationSystem {
+    type SystemData = (Read<'s, Terrain>, Read<'s, Time>, Write<'s, TileFrames>);
+
+    fn run(&mut self, (terrain, time, mut frames): Self::SystemData) {
+        let now = time.absolute_time_seconds();
+        for &tile in LevelTile::ALL.iter() {
+            match terrain
+                .sprite_at(tile, now)
+                .or_else(|| fallback_sprite(tile))
+            {
+                Some(sprite) => frames.sprites.insert(tile, sprite),
+                None => frames.sprites.remove(&tile),
+            };
+        }
+    }
+}
+
+/// The original dirt and grass art, for tiles the terrain data doesn't
+/// give a sprite.
+fn fallback_sprite(tile: LevelTile) -> Option<usize> {
+    match tile {
+        LevelTile::Plain | LevelTile::Road => Some(0),
+        LevelTile::Grass | LevelTile::Swamp => Some(1),
+        _ => None,
+    }
+}
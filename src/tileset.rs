@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use crate::{
+    data::Terrain,
+    level::{Level, LevelTile},
+    states::game::TERRAIN_SHEET,
+};
+
+/// The tilesets a level is drawn with, one tilemap layer each, and the
+/// layer each kind of tile is drawn on.
+#[derive(Debug, Clone, Default)]
+pub struct TileLayers {
+    /// Tileset names, in layer order.
+    pub sheets: Vec<String>,
+    layers: HashMap<LevelTile, usize>,
+}
+
+impl TileLayers {
+    /// Tiles go on the layer of the tileset their terrain data names, or on
+    /// the level's first tileset. Only tilesets some tile on the level uses
+    /// get a layer, so nothing else has to be loaded.
+    pub fn build(level: &Level, terrain: &Terrain) -> Self {
+        let default = level
+            .tilesets
+            .first()
+            .map(String::as_str)
+            .unwrap_or(TERRAIN_SHEET);
+        let mut sheets = vec![default.to_string()];
+        let mut layers = HashMap::new();
+        let kinds = level.tile_kinds();
+        for &tile in LevelTile::ALL.iter() {
+            let sheet = match terrain.properties(tile).sheet {
+                Some(sheet) => sheet,
+                None => continue,
+            };
+            if !kinds.contains(&tile) {
+                continue;
+            }
+            let layer = match sheets.iter().position(|s| *s == sheet) {
+                Some(layer) => layer,
+                None => {
+                    sheets.push(sheet);
+                    sheets.len() - 1
+                }
+            };
+            layers.insert(tile, layer);
+        }
+        Self { sheets, layers }
+    }
+
+    pub fn layer(&self, tile: LevelTile) -> usize {
+        self.layers.get(&tile).cloned().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TileProperties;
+
+    fn terrain(sheets: &[(LevelTile, &str)]) -> Terrain {
+        let mut terrain = Terrain::default();
+        for &(tile, sheet) in sheets {
+            terrain.tiles.insert(
+                tile,
+                TileProperties {
+                    sheet: Some(sheet.to_string()),
+                    ..TileProperties::default()
+                },
+            );
+        }
+        terrain
+    }
+
+    #[test]
+    fn tiles_default_to_the_level_tileset() {
+        let mut level = Level::from_ascii(1, "Test", ".,#").unwrap();
+        let layers = TileLayers::build(&level, &Terrain::default());
+        assert_eq!(layers.sheets, vec![TERRAIN_SHEET.to_string()]);
+        assert_eq!(layers.layer(LevelTile::Wall), 0);
+
+        level.tilesets = vec!["cave".to_string()];
+        let layers = TileLayers::build(&level, &Terrain::default());
+        assert_eq!(layers.sheets, vec!["cave".to_string()]);
+    }
+
+    #[test]
+    fn tiles_naming_a_tileset_get_its_layer() {
+        let level = Level::from_ascii(1, "Test", ".%#").unwrap();
+        let terrain = terrain(&[
+            (LevelTile::Water, "water"),
+            (LevelTile::Wall, "castle"),
+            (LevelTile::Lava, "volcano"),
+            (LevelTile::Plain, TERRAIN_SHEET),
+        ]);
+        let layers = TileLayers::build(&level, &terrain);
+        // No lava on the level, so the volcano tileset isn't needed
+        assert_eq!(layers.sheets, vec![TERRAIN_SHEET, "water", "castle"]);
+        assert_eq!(layers.layer(LevelTile::Plain), 0);
+        assert_eq!(layers.layer(LevelTile::Water), 1);
+        assert_eq!(layers.layer(LevelTile::Wall), 2);
+        assert_eq!(layers.layer(LevelTile::Grass), 0);
+    }
+}